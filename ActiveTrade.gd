@@ -37,6 +37,8 @@ func setup(db, texture_loader, trade):
 	
 	if _current_offer != null:
 		current_offer.text = str(_current_offer)
+		if _trade['count'] > 1:
+			current_offer.text += " for %d" % _trade['count']
 		spin_box.value = _current_offer
 		
 	target_entity.setup(_db, _texture_loader, _trade['target'])
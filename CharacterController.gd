@@ -14,5 +14,21 @@ func _input(event: InputEvent):
 					_server.try_move(Vector2(-1, 0))			
 				KEY_D:
 					_server.try_move(Vector2(1, 0))		
-				KEY_T: 
-					_server.try_trade()	
+				KEY_Q:
+					_server.try_move(Vector2(-1, -1))
+				KEY_E:
+					_server.try_move(Vector2(1, -1))
+				KEY_Z:
+					_server.try_move(Vector2(-1, 1))
+				KEY_C:
+					_server.try_move(Vector2(1, 1))
+				KEY_O:
+					_server.open_shop()
+				KEY_F5:
+					_server.reload_definitions(true)
+
+func _unhandled_input(event: InputEvent):
+	if event is InputEventMouseButton:
+		if event.pressed and event.button_index == BUTTON_LEFT:
+			var map = get_node("../UIController/MapController/GraphicTileMap")
+			_server.try_move_to(map.world_to_map(map.get_local_mouse_position()))
@@ -16,6 +16,8 @@ func update_entity(entity):
 	_sprite.texture = bundle.get_texture()
 	_sprite.scale = Vector2(20.0 / bundle.get_region().size.x, 15.0 / bundle.get_region().size.y)
 	_sprite.position = Vector2(0.0, -5.0)
+	var foreground = _db.get_foreground(entity)
+	_sprite.modulate = foreground if foreground != null else Color(1, 1, 1)
 	
 func clear_entity():
 	_sprite.region_rect = Rect2(0.0, 0.0, 0.0, 0.0)
@@ -23,7 +23,15 @@ func _on_LogicController_created_entities(entities):
 func _on_LogicController_deleted_entities(entities: Array):
 	for entity in entities: 
 		if _registered_entities.has(entity):
-			remove_child(entity)
+			var scene = _registered_entities[entity]
+			_registered_entities.erase(entity)
+			remove_child(scene)
+			scene.queue_free()
+
+func _on_LogicController_changed_entities(entities: Array):
+	for entity in entities: 
+		if _registered_entities.has(entity):
+			_registered_entities[entity].reload()
 
 func _on_Entity_on_entity_click(entity):
 	pass
@@ -5,6 +5,8 @@ signal done()
 var _db = null 
 var _player = null 
 var _other = null 
+# Giving to a customer offers the item for sale instead of handing it over.
+var _selling = false
 
 onready var player = get_node("Player")
 onready var other = get_node("Other")
@@ -13,16 +15,21 @@ onready var other = get_node("Other")
 func _ready():
 	player.setup(_db, _player)
 	other.setup(_db, _other)
+	other.button.visible = not _selling
 	
-func setup(db, player, other): 
+func setup(db, player, other, selling = false): 
 	_db = db
 	_player = player
 	_other = other
+	_selling = selling
 
-func _on_Other_on_take(item):
-	_db.try_take(_other)
+func _on_Other_on_take(item, count):
+	_db.try_take(_other, item, count)
 	emit_signal("done")
 	
-func _on_Player_on_give(item):
-	_db.try_put(_other, item)
+func _on_Player_on_give(item, count):
+	if _selling:
+		_db.try_start_trade(_other, item, count)
+	else:
+		_db.try_put(_other, item, count)
 	emit_signal("done")
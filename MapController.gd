@@ -16,6 +16,9 @@ var ATLAS = {}
 var tile_callbacks = {
 	'#': funcref(self, "load_wall"),
 	'.': funcref(self, "load_floor"),
+	'+': funcref(self, "load_floor"),
+	'>': funcref(self, "load_floor"),
+	'<': funcref(self, "load_floor"),
 }
 
 # Called when the node enters the scene tree for the first time.
@@ -95,6 +98,8 @@ func load_map(tiles, width):
 	for x in range(width):
 		for y in range(height):
 			var tile = map_matrix[y][x]
+			if tile == ' ':
+				continue
 			var region
 			if tile_callbacks.has(tile): 
 				tile_callbacks[tile].call_func(x, y, map_matrix, graphic_map)	
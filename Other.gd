@@ -1,12 +1,13 @@
 extends Node2D
 
-signal on_take(item)
+signal on_take(item, count)
 
 # Declare member variables here. Examples:
 # var a = 2
 # var b = "text"
 var _parent_entity = null
 var _inventory_entities = []
+var _inventory_counts = []
 var _db = null
 
 onready var itemlist = get_node("ItemList")
@@ -16,11 +17,15 @@ func setup(db, entity):
 	_parent_entity = entity
 	_db = db
 
-func update_list(entities, names):
+func update_list(entities, names, counts):
 	_inventory_entities = entities
+	_inventory_counts = counts
 	itemlist.clear()
-	for name in names: 
-		itemlist.add_item(name)
+	for i in range(names.size()): 
+		if counts[i] > 1:
+			itemlist.add_item("%s x%d" % [names[i], counts[i]])
+		else:
+			itemlist.add_item(names[i])
 
 func _process(_delta):
 	if _db == null: return
@@ -28,17 +33,20 @@ func _process(_delta):
 	
 	var entities = []
 	var names = []
+	var counts = []
 	var new = inventory.size() != _inventory_entities.size()
 	for item in inventory:
 		new = new || not(item['entity'] in _inventory_entities)
 		entities.push_back(item['entity'])
 		names.push_back(item['name'])	
+		counts.push_back(item['count'])
+	new = new || counts != _inventory_counts
 	
 	if new:
-		update_list(entities, names)
+		update_list(entities, names, counts)
 
 func _on_Button_pressed():
 	if itemlist.is_anything_selected(): 
 		var index = itemlist.get_selected_items()[0]
-		emit_signal("on_take", _inventory_entities[index])
+		emit_signal("on_take", _inventory_entities[index], _inventory_counts[index])
 		
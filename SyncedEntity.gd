@@ -18,6 +18,9 @@ func _ready():
 	var entity = get_node("Entity")
 	entity.setup(_db, _texture_loader, _entity)
 
+func reload():
+	get_node("Entity").reload()
+
 func _process(delta):
 	if _entity == null: return
 	set_grid_pos(_db.get_position(_entity))
@@ -10,8 +10,14 @@ var _db = null
 onready var givetake_scene = preload("res://GiveTakePanel.tscn")
 onready var trade_scene = preload("res://TradePanel.tscn")
 onready var texture_loader = get_node("TextureLoader")
+onready var message_log = get_node("HUD/MessageLog")
+onready var phase_label = get_node("HUD/PhaseLabel")
+onready var day_summary = get_node("HUD/DaySummary")
 var active_panel = null 
 
+const MAX_LOG_LINES = 4
+var _log_lines = []
+
 # Called when the node enters the scene tree for the first time.
 func _ready():
 	_db = get_node("../LogicController")
@@ -30,10 +36,11 @@ func close_panel(scene):
 	active_panel = null
 
 func _on_EntityController_on_entity_click(entity):
-	if _db.is_display_case(entity): 
+	var selling = _db.is_customer(entity)
+	if _db.is_display_case(entity) or selling: 
 		var scene = givetake_scene.instance()
 		var player = _db.get_player()
-		scene.setup(_db, player, entity)
+		scene.setup(_db, player, entity, selling)
 		if active_panel != null: 
 			close_panel(active_panel)
 		active_panel = scene
@@ -51,3 +58,19 @@ func _on_LogicController_trade_event(trade):
 	active_panel = scene
 	get_node("HUD").add_child(scene)
 	
+
+func log_message(text):
+	_log_lines.append(text)
+	if _log_lines.size() > MAX_LOG_LINES:
+		_log_lines.pop_front()
+	message_log.text = PoolStringArray(_log_lines).join("\n")
+
+func _on_LogicController_action_rejected(reason):
+	log_message(reason)
+
+func _on_LogicController_phase_changed(day, phase):
+	phase_label.text = "Day %d: %s" % [day, phase]
+
+func _on_LogicController_day_summary(ledger):
+	day_summary.dialog_text = "Day %d closed\n\nSales: %d\nRevenue: %d gold\nFailed sales: %d" % [ledger.day, ledger.sales, ledger.revenue, ledger.failed_sales]
+	day_summary.popup_centered()
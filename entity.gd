@@ -5,6 +5,7 @@ signal on_entity_click(entity)
 var _db = null 
 var _entity = null 
 var _texture_loader = null
+var _display_case = null
 
 onready var display_case_scene = preload("res://DisplayCase.tscn")
 onready var graphic_tileset: TileSet = preload("res://graphic_tileset.tres")
@@ -26,13 +27,16 @@ func reload():
 	sprite.region_enabled = true 
 	sprite.region_rect = bundle.get_region()
 	sprite.scale = Vector2(32.0 / bundle.get_region().size.x, 32.0 / bundle.get_region().size.y)
+	var foreground = _db.get_foreground(_entity)
+	if foreground != null:
+		sprite.modulate = foreground
 	
 	clickable.input_pickable = true
 	
-	if _db.is_display_case(_entity): 
-		var scene = display_case_scene.instance()
-		scene.setup(_db, _texture_loader, _entity)
-		add_child(scene)
+	if _display_case == null and _db.is_display_case(_entity): 
+		_display_case = display_case_scene.instance()
+		_display_case.setup(_db, _texture_loader, _entity)
+		add_child(_display_case)
 
 	
 func _on_Area2D_input_event(viewport, event, shape_idx):
@@ -0,0 +1,85 @@
+use god_four_am_server::map_builders::prefab::{self, PrefabLibrary};
+use god_four_am_server::map_builders::spawn_table::{self, SpawnTables};
+use god_four_am_server::serializers::entity_factory::{EntityFactory, DEFAULT_PATH};
+use god_four_am_server::serializers::FactoryError;
+use god_four_am_server::server::REQUIRED_IDS;
+use std::env;
+use std::process;
+
+/// Checks entity data files, e.g. `cargo run --bin validate -- entities.json`, including that
+/// they define every id the server builds by name. The prefabs and spawn tables are checked too,
+/// against every entity file; `--prefabs <path>` and `--spawn-tables <path>` read them from
+/// elsewhere.
+fn main() {
+    let mut paths = vec![];
+    let mut prefabs_path = prefab::DEFAULT_PATH.to_string();
+    let mut spawn_tables_path = spawn_table::DEFAULT_PATH.to_string();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--prefabs" => &mut prefabs_path,
+            "--spawn-tables" => &mut spawn_tables_path,
+            _ => {
+                paths.push(arg);
+                continue;
+            }
+        };
+        match args.next() {
+            Some(path) => *target = path,
+            None => {
+                eprintln!("{} needs a path", arg);
+                process::exit(2);
+            }
+        }
+    }
+    if paths.is_empty() {
+        paths.push(DEFAULT_PATH.to_string());
+    }
+    let mut failed = false;
+    let mut report = |path: &str, result: Result<(), FactoryError>| match result {
+        Ok(()) => println!("{}: ok", path),
+        Err(error) => {
+            failed = true;
+            eprintln!("{}: {}", path, error);
+        }
+    };
+    let prefabs = match PrefabLibrary::try_load_from(&prefabs_path) {
+        Ok(prefabs) => {
+            report(&prefabs_path, Ok(()));
+            Some(prefabs)
+        }
+        Err(error) => {
+            report(&prefabs_path, Err(error));
+            None
+        }
+    };
+    let spawn_tables = match SpawnTables::try_load_from(&spawn_tables_path) {
+        Ok(spawn_tables) => {
+            report(&spawn_tables_path, Ok(()));
+            Some(spawn_tables)
+        }
+        Err(error) => {
+            report(&spawn_tables_path, Err(error));
+            None
+        }
+    };
+    for path in paths.iter() {
+        let factory = match EntityFactory::try_load_from(path) {
+            Ok(factory) => factory,
+            Err(error) => {
+                report(path, Err(error));
+                continue;
+            }
+        };
+        report(path, factory.check_defines(&REQUIRED_IDS));
+        if let Some(prefabs) = &prefabs {
+            report(&format!("{} against {}", prefabs_path, path), prefabs.check_ids(&factory));
+        }
+        if let Some(spawn_tables) = &spawn_tables {
+            report(&format!("{} against {}", spawn_tables_path, path), spawn_tables.check_ids(&factory));
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color::new(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        let color = match name.to_ascii_lowercase().as_str() {
+            "black" => BLACK,
+            "blue" => BLUE,
+            "grey" | "gray" => GREY,
+            "red" => RED,
+            "tan" => TAN,
+            "white" => WHITE,
+            "yellow" => YELLOW,
+            "green" => GREEN,
+            _ => return None,
+        };
+        Some(color)
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, ColorParseError> {
+        let invalid = || ColorParseError::InvalidHex(hex.to_string());
+        if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let digits: Vec<u8> = match hex.len() {
+            // #rgb and #rgba repeat each digit, so #f80 is #ff8800.
+            3 | 4 => hex
+                .chars()
+                .map(|ch| u8::from_str_radix(&ch.to_string().repeat(2), 16))
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid())?,
+            6 | 8 => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+        let alpha = digits.get(3).cloned().unwrap_or(255);
+        Ok(Color::from_rgba8(digits[0], digits[1], digits[2], alpha))
+    }
+
+    fn from_function(name: &str, args: &str) -> Result<Self, ColorParseError> {
+        let invalid = || ColorParseError::InvalidFunction(format!("{}({})", name, args));
+        let args: Vec<&str> = args.split(',').map(|arg| arg.trim()).collect();
+        let expected = if name == "rgba" { 4 } else { 3 };
+        if args.len() != expected {
+            return Err(invalid());
+        }
+        let channels = args[..3]
+            .iter()
+            .map(|arg| arg.parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let alpha = match args.get(3) {
+            Some(alpha) => match alpha.parse::<f32>() {
+                Ok(alpha) if (0.0..=1.0).contains(&alpha) => alpha,
+                _ => return Err(invalid()),
+            },
+            None => 1.0,
+        };
+        let color = Color::from_rgba8(channels[0], channels[1], channels[2], 255);
+        Ok(Color { a: alpha, ..color })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorParseError {
+    Empty,
+    InvalidHex(String),
+    InvalidFunction(String),
+    UnknownName(String),
+}
+
+impl fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColorParseError::Empty => write!(f, "colour is empty"),
+            ColorParseError::InvalidHex(hex) => write!(f, "{:?} is not a valid hex colour", hex),
+            ColorParseError::InvalidFunction(function) => {
+                write!(f, "{:?} is not a valid rgb() or rgba() colour", function)
+            }
+            ColorParseError::UnknownName(name) => write!(f, "{:?} is not a known colour", name),
+        }
+    }
+}
+
+impl std::error::Error for ColorParseError {}
+
+/// Accepts `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb(r, g, b)`, `rgba(r, g, b, a)` with
+/// alpha in 0..=1, and the names of the constants below.
+impl FromStr for Color {
+    type Err = ColorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ColorParseError::Empty);
+        }
+        if s.starts_with('#') {
+            return Color::from_hex(&s[1..]);
+        }
+        if let (Some(open), true) = (s.find('('), s.ends_with(')')) {
+            let name = s[..open].trim().to_ascii_lowercase();
+            if name == "rgb" || name == "rgba" {
+                return Color::from_function(&name, &s[open + 1..s.len() - 1]);
+            }
+        }
+        Color::named(s).ok_or_else(|| ColorParseError::UnknownName(s.to_string()))
+    }
+}
+
+pub const BLACK: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 1.0,
+};
+pub const BLUE: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 1.0,
+    a: 1.0,
+};
+pub const GREY: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+pub const RED: Color = Color {
+    r: 0.9,
+    g: 0.1,
+    b: 0.1,
+    a: 1.0,
+};
+
+pub const TAN: Color = Color {
+    r: 232.0 / 255.0,
+    g: 166.0 / 255.0,
+    b: 80.0 / 255.0,
+    a: 1.0,
+};
+pub const WHITE: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 1.0,
+};
+pub const YELLOW: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 0.0,
+    a: 1.0,
+};
+pub const GREEN: Color = Color {
+    r: 0.1,
+    g: 0.8,
+    b: 0.1,
+    a: 1.0,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats() {
+        assert_eq!("#ffff00".parse(), Ok(YELLOW));
+        assert_eq!("#FF0".parse(), Ok(YELLOW));
+        assert_eq!("#0000ff80".parse::<Color>().unwrap().a, 128.0 / 255.0);
+        assert_eq!("rgb(0, 0, 255)".parse(), Ok(BLUE));
+        assert_eq!("rgba(255, 255, 255, 0.5)".parse(), Ok(Color::new(1.0, 1.0, 1.0, 0.5)));
+        assert_eq!(" Tan ".parse(), Ok(TAN));
+        assert_eq!("gray".parse(), Ok(GREY));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |s: &str| s.parse::<Color>().unwrap_err();
+        assert_eq!(error(""), ColorParseError::Empty);
+        assert_eq!(error("#ff000"), ColorParseError::InvalidHex("ff000".to_string()));
+        assert_eq!(error("#gg0000"), ColorParseError::InvalidHex("gg0000".to_string()));
+        assert_eq!(error("rgb(256, 0, 0)"), ColorParseError::InvalidFunction("rgb(256, 0, 0)".to_string()));
+        assert_eq!(error("rgb(1, 2)"), ColorParseError::InvalidFunction("rgb(1, 2)".to_string()));
+        assert_eq!(error("rgba(1, 2, 3, 2)"), ColorParseError::InvalidFunction("rgba(1, 2, 3, 2)".to_string()));
+        assert_eq!(error("chartreuse"), ColorParseError::UnknownName("chartreuse".to_string()));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::geom::Point;
+use crate::glyph::Glyph;
+use legion::prelude::Entity;
+use crate::resources::trade_handler::TradeRequest;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Into<Point> for Position {
+    fn into(self) -> Point {
+        (self.x, self.y).into()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Renderable {
+    pub glyph: Glyph,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Player;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Name {
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TurnState {
+    PENDING,
+    ACTIVE,
+    DONE,
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tradeable {
+    pub request: Option<TradeRequest>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveTurn {
+    pub state: TurnState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileBlocker;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Priority {
+    pub value: u8,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayCabinet;
+
+/// The factory id an entity was built from, so reloaded definitions can be applied to it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Prototype {
+    pub id: String,
+}
+
+/// Replaces `Position` while an entity waits on a level the player isn't on.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Parked {
+    pub depth: i32,
+    pub position: Point,
+}
+
+/// Tiles still to walk, one per turn, nearest first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MovePlan {
+    pub steps: Vec<Point>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Viewshed {
+    pub range: i32,
+    #[serde(skip)]
+    pub visible_tiles: Vec<Point>,
+    /// Where `visible_tiles` was computed from; `None` forces a recompute.
+    #[serde(skip)]
+    pub origin: Option<Point>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inventory {
+    pub contents: Vec<Entity>,
+    pub capacity: u8,
+}
+
+impl Inventory {
+    pub fn is_full(&self) -> bool {
+        self.contents.len() >= self.capacity as usize
+    }
+}
+
+/// How many units one stackable item entity stands for, up to `max`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    pub count: u32,
+    pub max: u32,
+}
+
+impl Quantity {
+    pub fn room(&self) -> u32 {
+        self.max.saturating_sub(self.count)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CustomerState {
+    Entering,
+    Browsing,
+    Trading,
+    Leaving,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Customer {
+    pub state: CustomerState,
+    pub target: Option<Entity>,
+    pub exit: Point,
+    pub patience: u32,
+    /// Turns in a row a leaving customer has found no way out.
+    pub stuck: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Negotiator {
+    pub policy: String,
+    pub patience: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Wallet {
+    pub gold: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NetworkId(pub u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn multiplier(&self) -> f32 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Uncommon => 1.5,
+            Rarity::Rare => 2.5,
+            Rarity::Legendary => 5.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Value {
+    pub base: u32,
+    pub rarity: Rarity,
+}
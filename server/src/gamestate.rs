@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum RunState {
+    Paused,
+    Initializing,
+    MapGeneration,
+    // Morning: the shop is shut while the player arranges stock.
+    Restock,
+    // Opening hours, customers arrive.
+    Running,
+    // No new customers, the ones inside finish up and leave.
+    Closing,
+    // The day's ledger is tallied before the next morning.
+    EndOfDay,
+}
+
+impl RunState {
+    pub fn is_simulating(&self) -> bool {
+        match self {
+            RunState::Restock | RunState::Running | RunState::Closing => true,
+            _ => false,
+        }
+    }
+}
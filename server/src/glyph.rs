@@ -1,6 +1,7 @@
 use crate::color::{Color, GREY};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Glyph {
     pub ch: char,
     pub foreground: Option<Color>,
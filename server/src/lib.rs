@@ -0,0 +1,17 @@
+#![feature(vec_remove_item)]
+
+pub mod color;
+pub mod component;
+pub mod gamestate;
+pub mod geom;
+pub mod glyph;
+pub mod map;
+pub mod map_builders;
+pub mod message;
+pub mod resources;
+pub mod rng;
+pub mod save;
+pub mod serializers;
+pub mod server;
+pub mod stacks;
+pub mod systems;
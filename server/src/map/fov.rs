@@ -0,0 +1,203 @@
+use super::{Map, TileType};
+use crate::geom::Point;
+
+// Symmetric shadowcasting, after https://www.albertford.com/shadowcasting/
+// Slopes are kept as exact fractions so tiles on a shadow edge are classified the same way
+// from either end.
+
+#[derive(Clone, Copy)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+impl Slope {
+    fn new(num: i32, den: i32) -> Self {
+        Self { num, den }
+    }
+
+    /// The slope through the left edge of `col` at `depth`.
+    fn of(depth: i32, col: i32) -> Self {
+        Self::new(2 * col - 1, 2 * depth)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Cardinal {
+    North,
+    East,
+    South,
+    West,
+}
+
+struct Quadrant {
+    cardinal: Cardinal,
+    origin: Point,
+}
+
+impl Quadrant {
+    fn transform(&self, depth: i32, col: i32) -> Point {
+        let (x, y) = (self.origin.x, self.origin.y);
+        match self.cardinal {
+            Cardinal::North => Point::new(x + col, y - depth),
+            Cardinal::South => Point::new(x + col, y + depth),
+            Cardinal::East => Point::new(x + depth, y + col),
+            Cardinal::West => Point::new(x - depth, y + col),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    fn min_col(&self) -> i32 {
+        // round_ties_up(depth * start)
+        (2 * self.depth * self.start.num + self.start.den).div_euclid(2 * self.start.den)
+    }
+
+    fn max_col(&self) -> i32 {
+        // round_ties_down(depth * end)
+        -(self.end.den - 2 * self.depth * self.end.num).div_euclid(2 * self.end.den)
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+
+    fn is_symmetric(&self, col: i32) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+}
+
+struct Scan<'a> {
+    map: &'a Map,
+    range: i32,
+    visible: Vec<Point>,
+}
+
+impl<'a> Scan<'a> {
+    fn is_opaque(&self, point: Point) -> bool {
+        !self.map.in_bounds(point) || self.map.get_type(point) == TileType::Wall
+    }
+
+    fn reveal(&mut self, point: Point, depth: i32, col: i32) {
+        if self.map.in_bounds(point) && depth * depth + col * col <= self.range * self.range {
+            self.visible.push(point);
+        }
+    }
+
+    fn scan(&mut self, quadrant: &Quadrant, mut row: Row) {
+        if row.depth > self.range {
+            return;
+        }
+        let mut previous: Option<bool> = None;
+        for col in row.min_col()..=row.max_col() {
+            let point = quadrant.transform(row.depth, col);
+            let opaque = self.is_opaque(point);
+            if opaque || row.is_symmetric(col) {
+                self.reveal(point, row.depth, col);
+            }
+            if previous == Some(true) && !opaque {
+                row.start = Slope::of(row.depth, col);
+            }
+            if previous == Some(false) && opaque {
+                let mut next = row.next();
+                next.end = Slope::of(row.depth, col);
+                self.scan(quadrant, next);
+            }
+            previous = Some(opaque);
+        }
+        if previous == Some(false) {
+            self.scan(quadrant, row.next());
+        }
+    }
+}
+
+/// Every tile visible from `origin` within `range`, walls included. Tiles may repeat.
+pub fn field_of_view(map: &Map, origin: Point, range: i32) -> Vec<Point> {
+    let mut scan = Scan {
+        map,
+        range,
+        visible: vec![],
+    };
+    if map.in_bounds(origin) {
+        scan.visible.push(origin);
+    }
+    for cardinal in [Cardinal::North, Cardinal::East, Cardinal::South, Cardinal::West].iter() {
+        let quadrant = Quadrant {
+            cardinal: *cardinal,
+            origin,
+        };
+        let first = Row {
+            depth: 1,
+            start: Slope::new(-1, 1),
+            end: Slope::new(1, 1),
+        };
+        scan.scan(&quadrant, first);
+    }
+    scan.visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room() -> Map {
+        let mut map = Map::new((9, 9), 0);
+        for x in 1..8 {
+            for y in 1..8 {
+                map.set_type(Point::new(x, y), TileType::Floor);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn test_open_room_is_fully_visible() {
+        let map = room();
+        let visible = field_of_view(&map, Point::new(4, 4), 10);
+        for x in 0..9 {
+            for y in 0..9 {
+                assert!(visible.contains(&Point::new(x, y)), "({}, {}) should be visible", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_walls_cast_shadows() {
+        let mut map = room();
+        map.set_type(Point::new(4, 3), TileType::Wall);
+        let visible = field_of_view(&map, Point::new(4, 4), 10);
+        assert!(visible.contains(&Point::new(4, 3)));
+        assert!(!visible.contains(&Point::new(4, 2)));
+        assert!(!visible.contains(&Point::new(4, 1)));
+    }
+
+    #[test]
+    fn test_visibility_is_symmetric() {
+        let mut map = room();
+        map.set_type(Point::new(3, 3), TileType::Wall);
+        map.set_type(Point::new(5, 5), TileType::Wall);
+        let floors: Vec<Point> = (0..81)
+            .map(|index| map.index_to_point(index))
+            .filter(|point| map.get_type(*point) == TileType::Floor)
+            .collect();
+        for a in floors.iter() {
+            let from_a = field_of_view(&map, *a, 10);
+            for b in floors.iter() {
+                if from_a.contains(b) {
+                    assert!(field_of_view(&map, *b, 10).contains(a), "{:?} sees {:?}", a, b);
+                }
+            }
+        }
+    }
+}
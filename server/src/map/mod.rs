@@ -1,53 +1,44 @@
 use crate::geom::{Point, Vector};
+use crate::resources::movement_rules::MovementRules;
 use legion::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub mod fov;
+pub mod pathfinding;
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     Digging,
+    Door,
+    DownStairs,
+    UpStairs,
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct TilePos(pub i32, pub i32, pub i32);
 
 impl TilePos {
-    fn push_if_under_cost(
+    pub fn successors(
         &self,
         map: &Map,
-        succ: &mut Vec<(TilePos, i32)>,
-        offset: (i32, i32),
+        rules: &MovementRules,
         max_cost: i32,
-    ) {
-        if !map.blocked[map.coord_to_index(self.0 + offset.0, self.1 + offset.1)] {
-            let agg_cost = self.2 + 1;
-            if agg_cost <= max_cost {
-                succ.push((TilePos(self.0 + offset.0, self.1 + offset.1, agg_cost), 1));
-            }
-        }
-    }
-
-    pub fn successors(&self, map: &Map, max_cost: i32) -> Vec<(TilePos, i32)> {
-        let (width, height) = map.size.to_tuple();
-        let mut succ = vec![];
-
-        if self.1 < height - 1 {
-            self.push_if_under_cost(map, &mut succ, (0, 1), max_cost)
-        }
-        if self.1 > 0 {
-            self.push_if_under_cost(map, &mut succ, (0, -1), max_cost)
-        }
-        if self.0 < width - 1 {
-            self.push_if_under_cost(map, &mut succ, (1, 0), max_cost)
-        }
-        if self.0 > 0 {
-            self.push_if_under_cost(map, &mut succ, (-1, 0), max_cost)
+    ) -> Vec<(TilePos, i32)> {
+        let agg_cost = self.2 + 1;
+        if agg_cost > max_cost {
+            return vec![];
         }
-        succ
+        rules
+            .neighbours(map, Point::new(self.0, self.1))
+            .into_iter()
+            .map(|point| (TilePos(point.x, point.y, agg_cost), 1))
+            .collect()
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub size: Vector,
@@ -55,6 +46,7 @@ pub struct Map {
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
     pub depth: i32,
+    #[serde(skip)]
     pub tile_content: Vec<Option<Entity>>,
 }
 
@@ -77,12 +69,23 @@ impl Map {
         ((y as usize) * self.size.x as usize) + x as usize
     }
 
+    pub fn index_to_point(&self, index: usize) -> Point {
+        let x = index as i32 % self.size.x;
+        let y = index as i32 / self.size.x;
+        (x, y).into()
+    }
+
     pub fn point_to_index(&self, point: Point) -> usize {
         ((point.y as usize) * self.size.x as usize) + point.x as usize
     }
 
+    pub fn in_bounds(&self, point: Point) -> bool {
+        point.x >= 0 && point.y >= 0 && point.x < self.size.x && point.y < self.size.y
+    }
+
+    /// Out-of-bounds points count as blocked.
     pub fn is_blocked(&self, point: Point) -> bool {
-        self.blocked[self.coord_to_index(point.x, point.y)]
+        !self.in_bounds(point) || self.blocked[self.point_to_index(point)]
     }
 
     pub fn get_type(&self, point: Point) -> TileType {
@@ -105,6 +108,19 @@ impl Map {
         }
     }
 
+    pub fn doors(&self) -> Vec<Point> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Door)
+            .map(|(index, _)| self.index_to_point(index))
+            .collect()
+    }
+
+    pub fn is_revealed(&self, point: Point) -> bool {
+        self.revealed_tiles[self.point_to_index(point)]
+    }
+
     pub fn set_revealed(&mut self, point: Point) {
         let index = self.point_to_index(point);
         self.revealed_tiles[index] = true;
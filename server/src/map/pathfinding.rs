@@ -0,0 +1,247 @@
+use super::{Map, TilePos};
+use crate::geom::Point;
+use crate::resources::movement_rules::MovementRules;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// Whether tiles holding an entity (`Map::tile_content`) count as blocked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Occupancy {
+    Ignore,
+    Avoid,
+}
+
+fn is_unoccupied(map: &Map, point: Point, occupancy: Occupancy) -> bool {
+    occupancy == Occupancy::Ignore || map.tile_content[map.point_to_index(point)].is_none()
+}
+
+/// Legal steps from `point`. `goal` may be occupied so paths can end next to an entity's tile.
+fn neighbours(
+    map: &Map,
+    rules: &MovementRules,
+    point: Point,
+    occupancy: Occupancy,
+    goal: Option<Point>,
+) -> Vec<Point> {
+    rules
+        .neighbours(map, point)
+        .into_iter()
+        .filter(|next| Some(*next) == goal || is_unoccupied(map, *next, occupancy))
+        .collect()
+}
+
+/// Shortest path from `from` to `to`, excluding `from` and including `to`.
+pub fn a_star(
+    map: &Map,
+    rules: &MovementRules,
+    from: Point,
+    to: Point,
+    occupancy: Occupancy,
+) -> Option<Vec<Point>> {
+    if !map.in_bounds(from) || !map.in_bounds(to) {
+        return None;
+    }
+    if from == to {
+        return Some(vec![]);
+    }
+    let start = map.point_to_index(from);
+    let goal = map.point_to_index(to);
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<usize, usize> = HashMap::new();
+    let mut cost: HashMap<usize, u32> = HashMap::new();
+    cost.insert(start, 0);
+    let heuristic = |point: Point| rules.distance(point, to) as u32;
+    open.push(Reverse((heuristic(from), 0, start)));
+
+    while let Some(Reverse((_, current_cost, current))) = open.pop() {
+        if current == goal {
+            let mut path = vec![map.index_to_point(goal)];
+            let mut step = goal;
+            while let Some(previous) = came_from.get(&step) {
+                if *previous == start {
+                    break;
+                }
+                path.push(map.index_to_point(*previous));
+                step = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if current_cost > cost[&current] {
+            continue;
+        }
+        let point = map.index_to_point(current);
+        for next in neighbours(map, rules, point, occupancy, Some(to)) {
+            let index = map.point_to_index(next);
+            let next_cost = current_cost + 1;
+            if cost.get(&index).map_or(true, |known| next_cost < *known) {
+                cost.insert(index, next_cost);
+                came_from.insert(index, current);
+                open.push(Reverse((next_cost + heuristic(next), next_cost, index)));
+            }
+        }
+    }
+    None
+}
+
+/// Distance from the nearest source to every tile, for flow-field movement.
+pub struct DijkstraMap {
+    width: i32,
+    rules: MovementRules,
+    distances: Vec<Option<u32>>,
+}
+
+impl DijkstraMap {
+    pub fn new(map: &Map, rules: &MovementRules, sources: &[Point], occupancy: Occupancy) -> Self {
+        let mut distances = vec![None; map.tiles.len()];
+        let mut queue = VecDeque::new();
+        for source in sources.iter().filter(|source| map.in_bounds(**source)) {
+            distances[map.point_to_index(*source)] = Some(0);
+            queue.push_back(*source);
+        }
+        // Every step costs the same, so a breadth-first flood settles tiles in Dijkstra order.
+        while let Some(point) = queue.pop_front() {
+            let distance = distances[map.point_to_index(point)].unwrap();
+            for next in neighbours(map, rules, point, occupancy, None) {
+                let index = map.point_to_index(next);
+                if distances[index].is_none() {
+                    distances[index] = Some(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        Self {
+            width: map.size.x,
+            rules: *rules,
+            distances,
+        }
+    }
+
+    pub fn distance(&self, point: Point) -> Option<u32> {
+        if point.x < 0 || point.y < 0 || point.x >= self.width {
+            return None;
+        }
+        let index = (point.y * self.width + point.x) as usize;
+        self.distances.get(index).cloned().flatten()
+    }
+
+    /// The neighbour of `from` that is closest to a source, if any is closer than `from` itself.
+    pub fn next_step(&self, map: &Map, from: Point) -> Option<Point> {
+        let current = self.distance(from)?;
+        self.rules
+            .neighbours(map, from)
+            .into_iter()
+            .filter_map(|next| self.distance(next).map(|distance| (distance, next)))
+            .filter(|(distance, _)| *distance < current)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, next)| next)
+    }
+}
+
+/// Every tile reachable from `from` in at most `moves` steps, including `from`.
+pub fn reachable_within(
+    map: &Map,
+    rules: &MovementRules,
+    from: Point,
+    moves: i32,
+    occupancy: Occupancy,
+) -> Vec<Point> {
+    if !map.in_bounds(from) {
+        return vec![];
+    }
+    let mut seen = vec![false; map.tiles.len()];
+    seen[map.point_to_index(from)] = true;
+    let mut reachable = vec![from];
+    let mut queue = VecDeque::new();
+    queue.push_back(TilePos(from.x, from.y, 0));
+    while let Some(tile) = queue.pop_front() {
+        for (next, _) in tile.successors(map, rules, moves) {
+            let point = Point::new(next.0, next.1);
+            let index = map.point_to_index(point);
+            if seen[index] || !is_unoccupied(map, point, occupancy) {
+                continue;
+            }
+            seen[index] = true;
+            reachable.push(point);
+            queue.push_back(next);
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+    use crate::resources::movement_rules::Topology;
+
+    fn four_way() -> MovementRules {
+        MovementRules::new(Topology::FourWay)
+    }
+
+    fn corridor() -> Map {
+        // #########
+        // #.......#
+        // #######.#
+        // #.......#
+        // #########
+        let mut map = Map::new((9, 5), 0);
+        for x in 1..8 {
+            map.set_type(Point::new(x, 1), TileType::Floor);
+            map.set_type(Point::new(x, 3), TileType::Floor);
+        }
+        map.set_type(Point::new(7, 2), TileType::Floor);
+        map.refresh_blocked();
+        map
+    }
+
+    #[test]
+    fn test_a_star_follows_corridor() {
+        let map = corridor();
+        let path = a_star(&map, &four_way(), Point::new(1, 1), Point::new(1, 3), Occupancy::Ignore).unwrap();
+        assert_eq!(path.len(), 14);
+        assert_eq!(path.first(), Some(&Point::new(2, 1)));
+        assert_eq!(path.last(), Some(&Point::new(1, 3)));
+        assert!(path.iter().all(|point| !map.is_blocked(*point)));
+    }
+
+    #[test]
+    fn test_a_star_without_route() {
+        let mut map = corridor();
+        map.set_type(Point::new(7, 2), TileType::Wall);
+        map.refresh_blocked();
+        assert!(a_star(&map, &four_way(), Point::new(1, 1), Point::new(1, 3), Occupancy::Ignore).is_none());
+    }
+
+    #[test]
+    fn test_a_star_diagonal() {
+        let mut map = Map::new((5, 5), 0);
+        for x in 1..4 {
+            for y in 1..4 {
+                map.set_type(Point::new(x, y), TileType::Floor);
+            }
+        }
+        map.refresh_blocked();
+        let rules = MovementRules::new(Topology::EightWay);
+        let path = a_star(&map, &rules, Point::new(1, 1), Point::new(3, 3), Occupancy::Ignore);
+        assert_eq!(path, Some(vec![Point::new(2, 2), Point::new(3, 3)]));
+    }
+
+    #[test]
+    fn test_dijkstra_flows_downhill() {
+        let map = corridor();
+        let flow = DijkstraMap::new(&map, &four_way(), &[Point::new(1, 3)], Occupancy::Ignore);
+        assert_eq!(flow.distance(Point::new(1, 1)), Some(14));
+        assert_eq!(flow.distance(Point::new(0, 0)), None);
+        assert_eq!(flow.next_step(&map, Point::new(7, 1)), Some(Point::new(7, 2)));
+    }
+
+    #[test]
+    fn test_reachable_within() {
+        let map = corridor();
+        let reachable = reachable_within(&map, &four_way(), Point::new(1, 1), 3, Occupancy::Ignore);
+        assert_eq!(reachable.len(), 4);
+        assert!(reachable.contains(&Point::new(4, 1)));
+        assert!(!reachable.contains(&Point::new(5, 1)));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::map::TileType;
+use crate::map_builders::spawn_table::SpawnTable;
+use crate::map_builders::{BuiltMap, MetaMapBuilder};
+use crate::rng::GameRng;
+use rand::Rng;
+use std::collections::HashSet;
+
+/// Splits the map into `area_size` squares and picks up to `max_per_area` free floor tiles in
+/// each, so spawns spread out instead of clumping wherever the most floor is.
+pub struct AreaSpawns {
+    pub area_size: i32,
+    pub max_per_area: usize,
+    pub table: SpawnTable,
+}
+
+impl AreaSpawns {
+    pub fn new(area_size: i32, max_per_area: usize, table: SpawnTable) -> Self {
+        Self {
+            area_size,
+            max_per_area,
+            table,
+        }
+    }
+}
+
+impl MetaMapBuilder for AreaSpawns {
+    fn mutate(&mut self, rng: &mut GameRng, build_data: &mut BuiltMap) {
+        if self.area_size < 1 {
+            return;
+        }
+        let map = &build_data.map;
+        let start = build_data.starting_position.map(|start| map.point_to_index(start));
+        let mut taken: HashSet<usize> = build_data.spawn_list.iter().map(|(index, _)| *index).collect();
+        taken.extend(start);
+
+        let (width, height) = map.size.to_tuple();
+        let mut spawns = vec![];
+        for area_y in (0..height).step_by(self.area_size as usize) {
+            for area_x in (0..width).step_by(self.area_size as usize) {
+                let mut free = vec![];
+                for y in area_y..(area_y + self.area_size).min(height) {
+                    for x in area_x..(area_x + self.area_size).min(width) {
+                        let index = map.coord_to_index(x, y);
+                        if map.tiles[index] == TileType::Floor && !taken.contains(&index) {
+                            free.push(index);
+                        }
+                    }
+                }
+                let count = rng.gen_range(0, self.max_per_area + 1).min(free.len());
+                for _ in 0..count {
+                    let index = free.swap_remove(rng.gen_range(0, free.len()));
+                    if let Some(id) = self.table.roll(rng) {
+                        taken.insert(index);
+                        spawns.push((index, id));
+                    }
+                }
+            }
+        }
+        build_data.spawn_list.extend(spawns);
+    }
+}
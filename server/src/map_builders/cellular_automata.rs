@@ -0,0 +1,52 @@
+use crate::map::TileType;
+use crate::map_builders::{BuiltMap, MetaMapBuilder};
+use crate::rng::GameRng;
+
+/// Smooths a map by repeatedly applying the 4-5 rule: a tile becomes wall when it has more than
+/// four wall neighbours, or none at all.
+pub struct CellularAutomataBuilder {
+    pub iterations: u32,
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(iterations: u32) -> Self {
+        Self { iterations }
+    }
+}
+
+impl MetaMapBuilder for CellularAutomataBuilder {
+    fn mutate(&mut self, _: &mut GameRng, build_data: &mut BuiltMap) {
+        let (width, height) = build_data.map.size.to_tuple();
+        for _ in 0..self.iterations {
+            let mut tiles = build_data.map.tiles.clone();
+            for y in 1..height - 1 {
+                for x in 1..width - 1 {
+                    let index = build_data.map.coord_to_index(x, y);
+                    if build_data.map.tiles[index] == TileType::Door {
+                        continue;
+                    }
+                    let mut walls = 0;
+                    for dy in -1..=1 {
+                        for dx in -1..=1 {
+                            if (dx, dy) == (0, 0) {
+                                continue;
+                            }
+                            let neighbour = build_data.map.coord_to_index(x + dx, y + dy);
+                            if build_data.map.tiles[neighbour] == TileType::Wall {
+                                walls += 1;
+                            }
+                        }
+                    }
+                    tiles[index] = if walls > 4 || walls == 0 {
+                        TileType::Wall
+                    } else {
+                        TileType::Floor
+                    };
+                }
+            }
+            build_data.map.tiles = tiles;
+            build_data.take_snapshot();
+        }
+        build_data.map.refresh_blocked();
+    }
+}
@@ -0,0 +1,37 @@
+use crate::map_builders::spawn_table::SpawnTable;
+use crate::map_builders::{BuiltMap, MetaMapBuilder};
+use crate::rng::GameRng;
+
+/// Rolls stock for every `container` in the spawn list. The stock shares the container's tile,
+/// which the server reads as "put this inside".
+pub struct ContainerStock {
+    pub container: String,
+    pub table: SpawnTable,
+}
+
+impl ContainerStock {
+    pub fn new(container: &str, table: SpawnTable) -> Self {
+        Self {
+            container: container.to_string(),
+            table,
+        }
+    }
+}
+
+impl MetaMapBuilder for ContainerStock {
+    fn mutate(&mut self, rng: &mut GameRng, build_data: &mut BuiltMap) {
+        let containers: Vec<usize> = build_data
+            .spawn_list
+            .iter()
+            .filter(|(_, id)| *id == self.container)
+            .map(|(index, _)| *index)
+            .collect();
+        for index in containers {
+            for _ in 0..self.table.rolls {
+                if let Some(id) = self.table.roll(rng) {
+                    build_data.spawn_list.push((index, id));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::geom::Point;
+use crate::map::pathfinding::{DijkstraMap, Occupancy};
+use crate::map::TileType;
+use crate::map_builders::{BuiltMap, MetaMapBuilder};
+use crate::resources::movement_rules::MovementRules;
+use crate::rng::GameRng;
+
+/// Walls over every tile that can't be reached from the starting position, moving the start
+/// onto the nearest floor first if an earlier pass buried it.
+pub struct CullUnreachable {
+    pub rules: MovementRules,
+}
+
+impl CullUnreachable {
+    pub fn new() -> Self {
+        Self {
+            rules: MovementRules::default(),
+        }
+    }
+}
+
+fn nearest_floor(build_data: &BuiltMap, target: Point) -> Option<Point> {
+    let map = &build_data.map;
+    (0..map.tiles.len())
+        .filter(|index| map.tiles[*index] != TileType::Wall)
+        .map(|index| map.index_to_point(index))
+        .min_by_key(|point| (point.x - target.x).abs() + (point.y - target.y).abs())
+}
+
+impl MetaMapBuilder for CullUnreachable {
+    fn mutate(&mut self, _: &mut GameRng, build_data: &mut BuiltMap) {
+        build_data.map.refresh_blocked();
+        let centre: Point = (build_data.map.size / 2).to_tuple().into();
+        let start = match build_data.starting_position {
+            Some(start) if !build_data.map.is_blocked(start) => start,
+            start => match nearest_floor(build_data, start.unwrap_or(centre)) {
+                Some(start) => start,
+                None => return,
+            },
+        };
+        build_data.starting_position = Some(start);
+
+        let flow = DijkstraMap::new(&build_data.map, &self.rules, &[start], Occupancy::Ignore);
+        for index in 0..build_data.map.tiles.len() {
+            let point = build_data.map.index_to_point(index);
+            if build_data.map.tiles[index] != TileType::Wall && flow.distance(point).is_none() {
+                build_data.map.tiles[index] = TileType::Wall;
+            }
+        }
+        let map = &build_data.map;
+        build_data
+            .spawn_list
+            .retain(|(index, _)| map.tiles[*index] != TileType::Wall);
+        build_data.map.refresh_blocked();
+        build_data.take_snapshot();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::basic_builders::create_room;
+    use crate::map_builders::{BaseMapBuilder, MapBuilder};
+    use crate::geom::Rect;
+
+    struct TwoRooms;
+
+    impl BaseMapBuilder for TwoRooms {
+        fn build(&mut self, _: &mut GameRng, build_data: &mut BuiltMap) {
+            create_room(&mut build_data.map, &Rect::new((1, 1).into(), (3, 3).into()));
+            create_room(&mut build_data.map, &Rect::new((6, 1).into(), (3, 3).into()));
+            build_data.starting_position = Some((2, 2).into());
+            build_data.spawn_list.push((build_data.map.coord_to_index(7, 2), "club".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_culls_disconnected_room() {
+        use rand::SeedableRng;
+        let mut rng = GameRng::seed_from_u64(0);
+        let built = MapBuilder::new((10, 5).into(), 0, TwoRooms)
+            .with(CullUnreachable::new())
+            .build(&mut rng);
+        assert_eq!(built.map.get_type((2, 2).into()), TileType::Floor);
+        assert_eq!(built.map.get_type((7, 2).into()), TileType::Wall);
+        assert!(built.spawn_list.is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::geom::Point;
+use crate::map::{Map, TileType};
+use crate::map_builders::{BuiltMap, MetaMapBuilder};
+use crate::rng::GameRng;
+use rand::Rng;
+
+/// Places doors in corridor mouths. With rooms, every opening in a room's outline is a
+/// candidate; without them, any one-tile chokepoint is, and `one_in` thins them out.
+pub struct DoorPlacement {
+    pub one_in: u32,
+}
+
+impl DoorPlacement {
+    pub fn new() -> Self {
+        Self { one_in: 3 }
+    }
+}
+
+fn is_tile(map: &Map, point: Point, tile: TileType) -> bool {
+    map.in_bounds(point) && map.get_type(point) == tile
+}
+
+fn door_possible(map: &Map, point: Point) -> bool {
+    if !is_tile(map, point, TileType::Floor) {
+        return false;
+    }
+    let (x, y) = (point.x, point.y);
+    let neighbour_door = [(0, 1), (0, -1), (1, 0), (-1, 0)]
+        .iter()
+        .any(|(dx, dy)| is_tile(map, Point::new(x + dx, y + dy), TileType::Door));
+    let horizontal = is_tile(map, Point::new(x - 1, y), TileType::Floor)
+        && is_tile(map, Point::new(x + 1, y), TileType::Floor)
+        && is_tile(map, Point::new(x, y - 1), TileType::Wall)
+        && is_tile(map, Point::new(x, y + 1), TileType::Wall);
+    let vertical = is_tile(map, Point::new(x, y - 1), TileType::Floor)
+        && is_tile(map, Point::new(x, y + 1), TileType::Floor)
+        && is_tile(map, Point::new(x - 1, y), TileType::Wall)
+        && is_tile(map, Point::new(x + 1, y), TileType::Wall);
+    !neighbour_door && (horizontal || vertical)
+}
+
+impl MetaMapBuilder for DoorPlacement {
+    fn mutate(&mut self, rng: &mut GameRng, build_data: &mut BuiltMap) {
+        let candidates: Vec<Point> = match &build_data.rooms {
+            Some(rooms) => rooms
+                .iter()
+                .flat_map(|room| {
+                    let (left, top) = (room.origin.x - 1, room.origin.y - 1);
+                    let (right, bottom) = (room.max_x(), room.max_y());
+                    let columns = (left..=right).flat_map(move |x| vec![(x, top), (x, bottom)]);
+                    let rows = (top..=bottom).flat_map(move |y| vec![(left, y), (right, y)]);
+                    columns.chain(rows).map(Point::from).collect::<Vec<Point>>()
+                })
+                .collect(),
+            None => (0..build_data.map.tiles.len())
+                .map(|index| build_data.map.index_to_point(index))
+                .filter(|_| rng.gen_range(0, self.one_in.max(1)) == 0)
+                .collect(),
+        };
+        for point in candidates {
+            if door_possible(&build_data.map, point) {
+                build_data.map.set_type(point, TileType::Door);
+            }
+        }
+        build_data.map.refresh_blocked();
+        build_data.take_snapshot();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::basic_builders::SimpleMapBuilder;
+    use crate::map_builders::MapBuilder;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_doors_sit_on_room_outlines() {
+        let mut rng = GameRng::seed_from_u64(3);
+        let built = MapBuilder::new((80, 50).into(), 1, SimpleMapBuilder)
+            .with(DoorPlacement::new())
+            .build(&mut rng);
+        let rooms = built.rooms.as_ref().unwrap();
+        let doors = built.map.doors();
+        assert!(!doors.is_empty());
+        for door in doors {
+            let on_outline = rooms.iter().any(|room| {
+                let (left, top) = (room.min_x() - 1, room.min_y() - 1);
+                let (right, bottom) = (room.max_x(), room.max_y());
+                let within = door.x >= left && door.x <= right && door.y >= top && door.y <= bottom;
+                let on_edge = door.x == left || door.x == right || door.y == top || door.y == bottom;
+                within && on_edge
+            });
+            assert!(on_outline, "{:?} is not on a room outline", door);
+        }
+    }
+}
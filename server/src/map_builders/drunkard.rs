@@ -0,0 +1,185 @@
+use crate::geom::Point;
+use crate::map::{Map, TileType};
+use crate::map_builders::{BaseMapBuilder, BuiltMap};
+use crate::rng::GameRng;
+use rand::Rng;
+
+/// Where each digger after the first starts walking.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrunkSpawnMode {
+    Centre,
+    RandomFloor,
+    PreviousEnd,
+}
+
+/// Mirrors every brush stroke across the map's centre line(s).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symmetry {
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+pub struct DrunkardsWalkBuilder {
+    pub lifetime: u32,
+    pub floor_percent: f32,
+    pub brush_size: i32,
+    pub symmetry: Symmetry,
+    pub spawn_mode: DrunkSpawnMode,
+}
+// https://bfnightly.bracketproductions.com/rustbook/chapter_36.html
+impl BaseMapBuilder for DrunkardsWalkBuilder {
+    fn build(&mut self, rng: &mut GameRng, build_data: &mut BuiltMap) {
+        let starting_position: Point = ((build_data.map.size) / 2).to_tuple().into();
+        let total_tiles = build_data.map.size.x * build_data.map.size.y;
+        let desired_floor = (self.floor_percent * total_tiles as f32) as usize;
+        let mut digger_count = 0;
+        let mut previous_end = starting_position;
+        let mut floor_tile_count = build_data
+            .map
+            .tiles
+            .iter()
+            .filter(|tile| **tile == TileType::Floor)
+            .count();
+        build_data.starting_position = Some(starting_position.clone());
+        while floor_tile_count < desired_floor {
+            let mut did_something = false;
+            let mut position = match (digger_count, self.spawn_mode) {
+                (0, _) | (_, DrunkSpawnMode::Centre) => starting_position.clone(),
+                (_, DrunkSpawnMode::PreviousEnd) => previous_end,
+                (_, DrunkSpawnMode::RandomFloor) => {
+                    random_floor(&build_data.map, rng).unwrap_or(starting_position)
+                }
+            };
+            let mut current_life = self.lifetime;
+            while current_life > 0 {
+                if self.paint(&mut build_data.map, position) {
+                    did_something = true;
+                }
+                let stagger = rng.gen_range(0, 4);
+                match stagger {
+                    0 => {
+                        if position.x > 1 {
+                            position.x -= 1
+                        }
+                    }
+                    1 => {
+                        if position.x < build_data.map.size.x - 2 {
+                            position.x += 1
+                        }
+                    }
+                    2 => {
+                        if position.y > 1 {
+                            position.y -= 1
+                        }
+                    }
+                    _ => {
+                        if position.y < build_data.map.size.y - 2 {
+                            position.y += 1
+                        }
+                    }
+                }
+                current_life -= 1;
+            }
+            if did_something {
+                build_data.take_snapshot();
+            }
+
+            previous_end = position;
+            digger_count += 1;
+            for t in build_data.map.tiles.iter_mut() {
+                if *t == TileType::Digging {
+                    *t = TileType::Floor;
+                }
+            }
+            floor_tile_count = build_data
+                .map
+                .tiles
+                .iter()
+                .filter(|tile| **tile == TileType::Floor)
+                .count();
+        }
+    }
+}
+
+fn random_floor(map: &Map, rng: &mut GameRng) -> Option<Point> {
+    let floors: Vec<usize> = (0..map.tiles.len())
+        .filter(|index| map.tiles[*index] == TileType::Floor)
+        .collect();
+    if floors.is_empty() {
+        return None;
+    }
+    Some(map.index_to_point(floors[rng.gen_range(0, floors.len())]))
+}
+
+impl DrunkardsWalkBuilder {
+    /// Digs a brush stroke at `position`, mirrored according to `symmetry`. Returns whether any
+    /// wall was dug out.
+    fn paint(&self, map: &mut Map, position: Point) -> bool {
+        let (width, height) = map.size.to_tuple();
+        let size = self.brush_size.max(1);
+        let half = size / 2;
+        let mut dug = false;
+        for y in position.y - half..position.y - half + size {
+            for x in position.x - half..position.x - half + size {
+                let (mirror_x, mirror_y) = (width - 1 - x, height - 1 - y);
+                let points = match self.symmetry {
+                    Symmetry::None => vec![(x, y)],
+                    Symmetry::Horizontal => vec![(x, y), (mirror_x, y)],
+                    Symmetry::Vertical => vec![(x, y), (x, mirror_y)],
+                    Symmetry::Both => vec![(x, y), (mirror_x, y), (x, mirror_y), (mirror_x, mirror_y)],
+                };
+                for (x, y) in points {
+                    // Never dig the outer wall.
+                    if x < 1 || y < 1 || x > width - 2 || y > height - 2 {
+                        continue;
+                    }
+                    let point = Point::new(x, y);
+                    match map.get_type(point) {
+                        TileType::Door => continue,
+                        TileType::Wall => dug = true,
+                        _ => {}
+                    }
+                    map.set_type(point, TileType::Digging);
+                }
+            }
+        }
+        dug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::MapBuilder;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_both_axis_symmetry() {
+        // Even sizes have no centre tile, so they need checking as well as odd ones.
+        for (width, height) in [(41, 31), (80, 50)].iter().cloned() {
+            let mut rng = GameRng::seed_from_u64(5);
+            let built = MapBuilder::new(
+                (width, height).into(),
+                0,
+                DrunkardsWalkBuilder {
+                    lifetime: 100,
+                    floor_percent: 0.3,
+                    brush_size: 2,
+                    symmetry: Symmetry::Both,
+                    spawn_mode: DrunkSpawnMode::PreviousEnd,
+                },
+            )
+            .build(&mut rng);
+            let map = &built.map;
+            for y in 0..height {
+                for x in 0..width {
+                    let tile = map.get_type(Point::new(x, y));
+                    assert_eq!(tile, map.get_type(Point::new(width - 1 - x, y)));
+                    assert_eq!(tile, map.get_type(Point::new(x, height - 1 - y)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use super::prefab::{Prefab, PrefabBuilder};
+use crate::geom::Vector;
+use crate::map_builders::area_spawns::AreaSpawns;
+use crate::map_builders::basic_builders::SimpleMapBuilder;
+use crate::map_builders::cellular_automata::CellularAutomataBuilder;
+use crate::map_builders::container_stock::ContainerStock;
+use crate::map_builders::cull_unreachable::CullUnreachable;
+use crate::map_builders::door_placement::DoorPlacement;
+use crate::map_builders::drunkard::{DrunkSpawnMode, DrunkardsWalkBuilder, Symmetry};
+use crate::map_builders::spawn_table::{SpawnKey, SpawnTable};
+use crate::map_builders::{BuiltMap, MapBuilder};
+use crate::rng::GameRng;
+
+fn random_map(size: Vector, depth: i32) -> MapBuilder {
+    MapBuilder::new(size, depth, SimpleMapBuilder)
+        .with(DoorPlacement::new())
+        .with(CullUnreachable::new())
+}
+
+fn drunk_map(size: Vector, depth: i32) -> MapBuilder {
+    MapBuilder::new(
+        size,
+        depth,
+        DrunkardsWalkBuilder {
+            lifetime: 400,
+            floor_percent: 0.6,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            spawn_mode: DrunkSpawnMode::RandomFloor,
+        },
+    )
+    .with(CullUnreachable::new())
+}
+
+fn cellar_map(size: Vector, depth: i32, stock: SpawnTable) -> MapBuilder {
+    MapBuilder::new(
+        size,
+        depth,
+        DrunkardsWalkBuilder {
+            lifetime: 200,
+            floor_percent: 0.5,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+            spawn_mode: DrunkSpawnMode::RandomFloor,
+        },
+    )
+    .with(CellularAutomataBuilder::new(4))
+    .with(CullUnreachable::new())
+    .with(AreaSpawns::new(8, 2, stock))
+}
+
+fn market_district_map(size: Vector, depth: i32) -> MapBuilder {
+    MapBuilder::new(
+        size,
+        depth,
+        DrunkardsWalkBuilder {
+            lifetime: 100,
+            floor_percent: 0.45,
+            brush_size: 2,
+            symmetry: Symmetry::Both,
+            spawn_mode: DrunkSpawnMode::PreviousEnd,
+        },
+    )
+    .with(CullUnreachable::new())
+}
+
+pub fn random_builder(size: Vector, depth: i32, rng: &mut GameRng) -> BuiltMap {
+    random_map(size, depth).build(rng)
+}
+
+pub fn drunk_builder(size: Vector, depth: i32, rng: &mut GameRng) -> BuiltMap {
+    drunk_map(size, depth).build(rng)
+}
+
+pub fn cellar_builder(size: Vector, depth: i32, stock: &SpawnTable, rng: &mut GameRng) -> BuiltMap {
+    cellar_map(size, depth, stock.clone()).build(rng)
+}
+
+pub fn market_district_builder(size: Vector, depth: i32, rng: &mut GameRng) -> BuiltMap {
+    market_district_map(size, depth).build(rng)
+}
+
+/// Builds `prefab`, filling each display from `stock` if there is one.
+pub fn shop_builder(prefab: &Prefab, stock: Option<&SpawnTable>, rng: &mut GameRng) -> BuiltMap {
+    let builder = MapBuilder::new(prefab.size(), 0, PrefabBuilder::new(prefab.clone()));
+    match stock {
+        Some(stock) => builder.with(ContainerStock::new("display", stock.clone())).build(rng),
+        None => builder.build(rng),
+    }
+}
+
+/// Builds one of the named generators with its history kept, so it can be played back.
+pub fn preview_builder(name: &str, size: Vector, depth: i32, rng: &mut GameRng) -> Option<BuiltMap> {
+    let builder = match name {
+        "random" => random_map(size, depth),
+        "drunk" => drunk_map(size, depth),
+        // Previews only play back the layout, so there's nothing to stock.
+        "cellar" => cellar_map(size, depth, SpawnTable::uniform(SpawnKey::Depth(depth), &[])),
+        "market_district" => market_district_map(size, depth),
+        _ => return None,
+    };
+    Some(builder.keep_history().build(rng))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+    use crate::map_builders::prefab::PrefabLibrary;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_seed_reproduces_layout() {
+        let build = |seed| {
+            let mut rng = GameRng::seed_from_u64(seed);
+            random_builder((80, 50).into(), 1, &mut rng)
+        };
+        let first = build(42);
+        let second = build(42);
+        assert_eq!(first.map.tiles, second.map.tiles);
+        assert_eq!(first.starting_position, second.starting_position);
+
+        let mut rng = GameRng::seed_from_u64(7);
+        let first = drunk_builder((80, 50).into(), 1, &mut rng);
+        let mut rng = GameRng::seed_from_u64(7);
+        let second = drunk_builder((80, 50).into(), 1, &mut rng);
+        assert_eq!(first.map.tiles, second.map.tiles);
+    }
+
+    #[test]
+    fn test_preview_keeps_history() {
+        let mut rng = GameRng::seed_from_u64(2);
+        let built = preview_builder("drunk", (40, 30).into(), 1, &mut rng).unwrap();
+        assert!(built.history.len() > 1);
+        assert!(built.history.iter().all(|frame| frame.revealed_tiles.iter().all(|r| *r)));
+        assert!(preview_builder("nonsense", (40, 30).into(), 1, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_cellar_spawns_on_reachable_floor() {
+        let mut rng = GameRng::seed_from_u64(11);
+        let stock = SpawnTable::uniform(SpawnKey::Depth(1), &["love", "club"]);
+        let built = cellar_builder((40, 30).into(), 1, &stock, &mut rng);
+        let start = built.starting_position.unwrap();
+        assert_eq!(built.map.get_type(start), TileType::Floor);
+        for (index, _) in built.spawn_list.iter() {
+            assert_eq!(built.map.tiles[*index], TileType::Floor);
+        }
+    }
+
+    #[test]
+    fn test_shop_stocks_displays() {
+        let library = PrefabLibrary::parse(
+            r#"{ "prefabs": [ { "id": "kiosk", "layout": ["#####", "#@D.#", "#.D.#", "##+##"], "legend": { "D": "display" } } ] }"#,
+        )
+        .unwrap();
+        let stock = SpawnTable::uniform(SpawnKey::Shop("kiosk".to_string()), &["apple"]);
+        let mut rng = GameRng::seed_from_u64(0);
+        let built = shop_builder(library.get("kiosk").unwrap(), Some(&stock), &mut rng);
+        let tiles_of = |wanted: &str| -> Vec<usize> {
+            built
+                .spawn_list
+                .iter()
+                .filter(|(_, id)| id == wanted)
+                .map(|(index, _)| *index)
+                .collect()
+        };
+        assert_eq!(tiles_of("display").len(), 2);
+        assert_eq!(tiles_of("apple"), tiles_of("display"));
+    }
+}
@@ -1,19 +1,26 @@
 use crate::geom::{Point, Rect, Vector};
 use crate::map::Map;
-use rand::prelude::ThreadRng;
+use crate::rng::GameRng;
 
+pub mod area_spawns;
 pub mod basic_builders;
+pub mod cellular_automata;
+pub mod container_stock;
+pub mod cull_unreachable;
+pub mod door_placement;
 pub mod drunkard;
 pub mod factories;
+pub mod prefab;
 pub mod shop_builder;
+pub mod spawn_table;
 
 // Most of this taken from https://bfnightly.bracketproductions.com/rustbook/chapter_36.html
 pub trait BaseMapBuilder {
-    fn build(&mut self, rng: &mut ThreadRng, build_data: &mut BuiltMap);
+    fn build(&mut self, rng: &mut GameRng, build_data: &mut BuiltMap);
 }
 
 pub trait MetaMapBuilder {
-    fn mutate(&mut self, rng: &mut ThreadRng, build_data: &mut BuiltMap);
+    fn mutate(&mut self, rng: &mut GameRng, build_data: &mut BuiltMap);
 }
 
 pub struct MapBuilder {
@@ -41,7 +48,7 @@ impl MapBuilder {
         self
     }
 
-    pub fn build(mut self, rng: &mut ThreadRng) -> BuiltMap {
+    pub fn build(mut self, rng: &mut GameRng) -> BuiltMap {
         self.base.build(rng, &mut self.build_data);
         for mut metabuilder in self.builders.drain(..) {
             metabuilder.mutate(rng, &mut self.build_data)
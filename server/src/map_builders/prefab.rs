@@ -0,0 +1,242 @@
+use crate::geom::Vector;
+use crate::map::TileType;
+use crate::map_builders::{BaseMapBuilder, BuiltMap};
+use crate::rng::GameRng;
+use crate::serializers::entity_factory::EntityFactory;
+use crate::serializers::{FactoryError, Problem, SchemaError};
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::from_str;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "prefabs.json";
+
+const WALL: char = '#';
+const FLOOR: char = '.';
+const DOOR: char = '+';
+const START: char = '@';
+const STAIRS: char = '>';
+
+/// A hand-drawn layout. Anything in `legend` is floor with that entity spawned on top of it.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Prefab {
+    pub id: String,
+    pub layout: Vec<String>,
+    #[serde(default)]
+    pub legend: HashMap<char, String>,
+}
+
+impl Prefab {
+    pub fn size(&self) -> Vector {
+        let width = self.layout.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        (width as i32, self.layout.len() as i32).into()
+    }
+
+    fn validate(&self, path: &str, errors: &mut Vec<SchemaError>) {
+        let mut starts = 0;
+        for (y, row) in self.layout.iter().enumerate() {
+            for ch in row.chars() {
+                match ch {
+                    WALL | FLOOR | DOOR | STAIRS => {}
+                    START => starts += 1,
+                    ch if self.legend.contains_key(&ch) => {}
+                    ch => errors.push(SchemaError {
+                        path: format!("{}.layout[{}]", path, y),
+                        problem: Problem::UnknownTile(ch),
+                    }),
+                }
+            }
+        }
+        if starts != 1 {
+            errors.push(SchemaError {
+                path: format!("{}.layout", path),
+                problem: Problem::StartingPositions(starts),
+            });
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct PrefabData {
+    prefabs: Vec<Prefab>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrefabLibrary {
+    prefabs: Vec<Prefab>,
+}
+
+impl PrefabLibrary {
+    pub fn load() -> Self {
+        Self::load_from(DEFAULT_PATH)
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_load_from(path).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_load_from<P: AsRef<Path>>(path: P) -> Result<Self, FactoryError> {
+        let raw_string = fs::read_to_string(path)?;
+        Self::parse(&raw_string)
+    }
+
+    /// Parses a prefab file, reporting every problem rather than the first.
+    pub fn parse(raw_string: &str) -> Result<Self, FactoryError> {
+        let data: PrefabData = from_str(raw_string)?;
+        let mut errors = vec![];
+        for (index, prefab) in data.prefabs.iter().enumerate() {
+            prefab.validate(&format!("prefabs[{}]", index), &mut errors);
+        }
+        if data.prefabs.is_empty() {
+            errors.push(SchemaError {
+                path: "prefabs".to_string(),
+                problem: Problem::Empty,
+            });
+        }
+        if !errors.is_empty() {
+            return Err(FactoryError::Invalid(errors));
+        }
+        Ok(Self {
+            prefabs: data.prefabs,
+        })
+    }
+
+    /// Checks that every entity the legends place can be built.
+    pub fn check_ids(&self, factory: &EntityFactory) -> Result<(), FactoryError> {
+        let mut errors = vec![];
+        for (index, prefab) in self.prefabs.iter().enumerate() {
+            // Sorted, so the report doesn't depend on hash order.
+            let mut legend: Vec<(&char, &String)> = prefab.legend.iter().collect();
+            legend.sort();
+            for (ch, id) in legend {
+                if !factory.contains(id) {
+                    errors.push(SchemaError {
+                        path: format!("prefabs[{}].legend.{}", index, ch),
+                        problem: Problem::UnknownId(id.clone()),
+                    });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(FactoryError::Invalid(errors));
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Prefab> {
+        self.prefabs.iter().find(|prefab| prefab.id == id)
+    }
+
+    pub fn random(&self, rng: &mut GameRng) -> &Prefab {
+        &self.prefabs[rng.gen_range(0, self.prefabs.len())]
+    }
+}
+
+pub struct PrefabBuilder {
+    prefab: Prefab,
+}
+
+impl PrefabBuilder {
+    pub fn new(prefab: Prefab) -> Self {
+        Self { prefab }
+    }
+}
+
+impl BaseMapBuilder for PrefabBuilder {
+    fn build(&mut self, _: &mut GameRng, build_data: &mut BuiltMap) {
+        for (y, row) in self.prefab.layout.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let point = (x as i32, y as i32).into();
+                let index = build_data.map.point_to_index(point);
+                let tile = match ch {
+                    WALL => TileType::Wall,
+                    DOOR => TileType::Door,
+                    STAIRS => TileType::DownStairs,
+                    START => {
+                        build_data.starting_position = Some(point);
+                        TileType::Floor
+                    }
+                    FLOOR => TileType::Floor,
+                    ch => {
+                        if let Some(id) = self.prefab.legend.get(&ch) {
+                            build_data.spawn_list.push((index, id.clone()));
+                        }
+                        TileType::Floor
+                    }
+                };
+                build_data.map.set_type(point, tile);
+            }
+        }
+        build_data.map.refresh_blocked();
+        build_data.take_snapshot();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::MapBuilder;
+    use rand::SeedableRng;
+
+    const PREFABS: &str = r##"{
+        "prefabs": [
+            {
+                "id": "kiosk",
+                "layout": [
+                    "#####",
+                    "#@D.#",
+                    "#=..#",
+                    "##+##"
+                ],
+                "legend": { "D": "display", "=": "counter" }
+            }
+        ]
+    }"##;
+
+    #[test]
+    fn test_prefab_fills_built_map() {
+        let library = PrefabLibrary::parse(PREFABS).unwrap();
+        let prefab = library.get("kiosk").unwrap().clone();
+        let mut rng = GameRng::seed_from_u64(0);
+        let built = MapBuilder::new(prefab.size(), 0, PrefabBuilder::new(prefab)).build(&mut rng);
+
+        assert_eq!(built.map.size, (5, 4).into());
+        assert_eq!(built.starting_position, Some((1, 1).into()));
+        assert_eq!(built.map.doors(), vec![(2, 3).into()]);
+        let spawns: Vec<(usize, &str)> = built
+            .spawn_list
+            .iter()
+            .map(|(index, id)| (*index, id.as_str()))
+            .collect();
+        assert_eq!(spawns, vec![(7, "display"), (11, "counter")]);
+        assert!(!built.map.is_blocked((2, 1).into()));
+    }
+
+    #[test]
+    fn test_unknown_glyph() {
+        let errors = match PrefabLibrary::parse(r#"{ "prefabs": [ { "id": "bad", "layout": ["#@?#"] } ] }"#) {
+            Err(FactoryError::Invalid(errors)) => errors,
+            _ => panic!("expected the prefab to be rejected"),
+        };
+        assert_eq!(errors[0].path, "prefabs[0].layout[0]");
+        assert_eq!(errors[0].problem, Problem::UnknownTile('?'));
+    }
+
+    #[test]
+    fn test_legend_ids_must_exist() {
+        let library = PrefabLibrary::parse(PREFABS).unwrap();
+        let factory = EntityFactory::parse(
+            r#"{ "builder": [ { "id": "display", "name": "Display", "renderable": { "glyph": { "ch": "D", "render_order": 0 } } } ] }"#,
+        )
+        .unwrap();
+        let errors = match library.check_ids(&factory) {
+            Err(FactoryError::Invalid(errors)) => errors,
+            _ => panic!("expected the counter to be missing"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "prefabs[0].legend.=");
+        assert_eq!(errors[0].problem, Problem::UnknownId("counter".to_string()));
+    }
+}
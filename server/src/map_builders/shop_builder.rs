@@ -1,12 +1,12 @@
 use crate::geom::Rect;
 use crate::map::{Map, TileType};
-use crate::server::map_builders::{BaseMapBuilder, BuiltMap};
-use rand::prelude::ThreadRng;
+use crate::map_builders::{BaseMapBuilder, BuiltMap};
+use crate::rng::GameRng;
 
 pub struct ShopBuilder;
 
 impl BaseMapBuilder for ShopBuilder {
-    fn build(&mut self, _: &mut ThreadRng, build_data: &mut BuiltMap) {
+    fn build(&mut self, _: &mut GameRng, build_data: &mut BuiltMap) {
         let size: (i32, i32) = build_data.map.size.to_tuple();
         let map = &mut build_data.map;
         create_room(
@@ -14,6 +14,8 @@ impl BaseMapBuilder for ShopBuilder {
             &Rect::new((1, 1).into(), (size.0 - 2, size.1 - 2).into()),
         );
 
+        map.set_type((size.0 / 2, size.1 - 1).into(), TileType::Door);
+
         build_data.starting_position = Some((size.0 / 2, size.1 / 2).into());
     }
 }
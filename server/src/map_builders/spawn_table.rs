@@ -0,0 +1,267 @@
+use crate::component::Rarity;
+use crate::rng::GameRng;
+use crate::serializers::entity_factory::EntityFactory;
+use crate::serializers::{FactoryError, Problem, SchemaError};
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::from_str;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "spawn_tables.json";
+
+const TIERS: [Rarity; 4] = [Rarity::Common, Rarity::Uncommon, Rarity::Rare, Rarity::Legendary];
+
+/// What a table stocks: a level by depth, a shop by prefab id, or an entity's starting inventory.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnKey {
+    Depth(i32),
+    Shop(String),
+    Inventory(String),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpawnEntry {
+    pub id: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default = "default_rarity")]
+    pub rarity: Rarity,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_rarity() -> Rarity {
+    Rarity::Common
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+/// A roll first picks a rarity tier by its weight in `tiers`, then an entry of that tier by its
+/// own weight. Without `tiers`, every entry is weighed against every other.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpawnTable {
+    pub key: SpawnKey,
+    /// How many items each spawn point or container gets.
+    #[serde(default = "default_rolls")]
+    pub rolls: u32,
+    #[serde(default)]
+    pub tiers: Option<HashMap<Rarity, u32>>,
+    pub entries: Vec<SpawnEntry>,
+}
+
+impl SpawnTable {
+    pub fn uniform(key: SpawnKey, ids: &[&str]) -> Self {
+        Self {
+            key,
+            rolls: 1,
+            tiers: None,
+            entries: ids
+                .iter()
+                .map(|id| SpawnEntry {
+                    id: id.to_string(),
+                    weight: 1,
+                    rarity: Rarity::Common,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn roll(&self, rng: &mut GameRng) -> Option<String> {
+        let candidates: Vec<(&SpawnEntry, u32)> = match &self.tiers {
+            Some(tiers) => {
+                // Tiers are walked in a fixed order so a seed always rolls the same stock.
+                let available: Vec<(Rarity, u32)> = TIERS
+                    .iter()
+                    .filter(|tier| self.entries.iter().any(|entry| entry.rarity == **tier && entry.weight > 0))
+                    .map(|tier| (*tier, tiers.get(tier).cloned().unwrap_or(0)))
+                    .collect();
+                let tier = *pick_weighted(rng, &available)?;
+                self.entries
+                    .iter()
+                    .filter(|entry| entry.rarity == tier)
+                    .map(|entry| (entry, entry.weight))
+                    .collect()
+            }
+            None => self.entries.iter().map(|entry| (entry, entry.weight)).collect(),
+        };
+        pick_weighted(rng, &candidates).map(|entry| entry.id.clone())
+    }
+
+    fn validate(&self, path: &str, errors: &mut Vec<SchemaError>) {
+        if self.entries.iter().all(|entry| entry.weight == 0) {
+            errors.push(SchemaError {
+                path: format!("{}.entries", path),
+                problem: Problem::NeverRolls,
+            });
+        }
+    }
+}
+
+fn pick_weighted<'a, T>(rng: &mut GameRng, items: &'a [(T, u32)]) -> Option<&'a T> {
+    let total: u32 = items.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.gen_range(0, total);
+    for (item, weight) in items.iter() {
+        if roll < *weight {
+            return Some(item);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct SpawnTableData {
+    tables: Vec<SpawnTable>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpawnTables {
+    tables: Vec<SpawnTable>,
+}
+
+impl SpawnTables {
+    pub fn load() -> Self {
+        Self::load_from(DEFAULT_PATH)
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_load_from(path).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_load_from<P: AsRef<Path>>(path: P) -> Result<Self, FactoryError> {
+        let raw_string = fs::read_to_string(path)?;
+        Self::parse(&raw_string)
+    }
+
+    /// Parses a spawn table file, reporting every problem rather than the first.
+    pub fn parse(raw_string: &str) -> Result<Self, FactoryError> {
+        let data: SpawnTableData = from_str(raw_string)?;
+        let mut errors = vec![];
+        for (index, table) in data.tables.iter().enumerate() {
+            let path = format!("tables[{}]", index);
+            table.validate(&path, &mut errors);
+            if data.tables[..index].iter().any(|other| other.key == table.key) {
+                errors.push(SchemaError {
+                    path: format!("{}.key", path),
+                    problem: Problem::DuplicateTable(format!("{:?}", table.key)),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(FactoryError::Invalid(errors));
+        }
+        Ok(Self {
+            tables: data.tables,
+        })
+    }
+
+    /// Checks that everything the tables roll, and every inventory they stock, can be built.
+    pub fn check_ids(&self, factory: &EntityFactory) -> Result<(), FactoryError> {
+        let mut errors = vec![];
+        for (index, table) in self.tables.iter().enumerate() {
+            if let SpawnKey::Inventory(id) = &table.key {
+                if !factory.contains(id) {
+                    errors.push(SchemaError {
+                        path: format!("tables[{}].key", index),
+                        problem: Problem::UnknownId(id.clone()),
+                    });
+                }
+            }
+            for (slot, entry) in table.entries.iter().enumerate() {
+                if !factory.contains(&entry.id) {
+                    errors.push(SchemaError {
+                        path: format!("tables[{}].entries[{}].id", index, slot),
+                        problem: Problem::UnknownId(entry.id.clone()),
+                    });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(FactoryError::Invalid(errors));
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, key: &SpawnKey) -> Option<&SpawnTable> {
+        self.tables.iter().find(|table| &table.key == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const TABLES: &str = r#"{
+        "tables": [
+            {
+                "key": { "shop": "kiosk" },
+                "tiers": { "common": 1, "rare": 0 },
+                "entries": [
+                    { "id": "apple", "weight": 3 },
+                    { "id": "pear", "weight": 1 },
+                    { "id": "diamond", "rarity": "rare" }
+                ]
+            },
+            {
+                "key": { "depth": 1 },
+                "rolls": 2,
+                "entries": [ { "id": "carrot" } ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_roll_respects_tiers() {
+        let tables = SpawnTables::parse(TABLES).unwrap();
+        let table = tables.get(&SpawnKey::Shop("kiosk".to_string())).unwrap();
+        let mut rng = GameRng::seed_from_u64(1);
+        let rolls: Vec<String> = (0..200).filter_map(|_| table.roll(&mut rng)).collect();
+        assert_eq!(rolls.len(), 200);
+        assert!(rolls.iter().all(|id| id != "diamond"));
+        let apples = rolls.iter().filter(|id| *id == "apple").count();
+        assert!(apples > 100 && apples < 200, "{} apples", apples);
+
+        let cellar = tables.get(&SpawnKey::Depth(1)).unwrap();
+        assert_eq!(cellar.rolls, 2);
+        assert_eq!(cellar.roll(&mut rng), Some("carrot".to_string()));
+        assert!(tables.get(&SpawnKey::Depth(2)).is_none());
+    }
+
+    #[test]
+    fn test_shipped_tables_are_valid() {
+        let tables = SpawnTables::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../spawn_tables.json"));
+        let factory = EntityFactory::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../entities.json"));
+        if let Err(error) = tables.check_ids(&factory) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let error = SpawnTables::parse(
+            r#"{ "tables": [
+                { "key": { "depth": 1 }, "entries": [ { "id": "carrot" } ] },
+                { "key": { "depth": 1 }, "entries": [ { "id": "potato", "weight": 0 } ] }
+            ] }"#,
+        );
+        let errors = match error {
+            Err(FactoryError::Invalid(errors)) => errors,
+            _ => panic!("expected the tables to be rejected"),
+        };
+        assert_eq!(errors[0].path, "tables[1].entries");
+        assert_eq!(errors[0].problem, Problem::NeverRolls);
+        assert_eq!(errors[1].path, "tables[1].key");
+        assert_eq!(errors[1].problem, Problem::DuplicateTable("Depth(1)".to_string()));
+    }
+}
@@ -0,0 +1,34 @@
+use crate::color::Color;
+use crate::geom::Point;
+use legion::prelude::Entity;
+use crate::resources::trade_handler::{Trade, TradeMessage};
+use crate::resources::shop_day::Ledger;
+use crate::gamestate::RunState;
+
+#[derive(Clone)]
+pub enum Message {
+    TradeEvent(Trade),
+    LogEvent(String),
+    ActionRejected(String),
+    PhaseChanged {
+        day: u32,
+        phase: RunState
+    },
+    DaySummary(Ledger),
+    MapRevealed,
+    MapUpdated,
+    /// These entities' `Renderable` or `Name` changed after a definitions reload.
+    EntitiesChanged(Vec<Entity>)
+}
+
+#[derive(Clone)]
+pub enum Action {
+    TradeUpdate(TradeMessage),
+    Transaction {
+        source: Entity,
+        target: Entity,
+        object: Entity,
+        count: u32,
+        value: u32
+    }
+}
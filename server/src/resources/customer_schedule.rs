@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// When the next customer may walk in and how many can be in the shop at once.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomerSchedule {
+    pub next_arrival: u64,
+    pub max_customers: usize,
+}
+
+impl CustomerSchedule {
+    pub fn new(next_arrival: u64) -> Self {
+        Self {
+            next_arrival,
+            max_customers: 3,
+        }
+    }
+}
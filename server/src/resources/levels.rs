@@ -0,0 +1,50 @@
+use crate::geom::Point;
+use crate::map::Map;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const STREET_DEPTH: i32 = -1;
+pub const SHOP_DEPTH: i32 = 0;
+pub const CELLAR_DEPTH: i32 = 1;
+
+/// Stepping onto `position` on level `depth` takes the player to level `target`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelLink {
+    pub depth: i32,
+    pub position: Point,
+    pub target: i32,
+}
+
+/// Every level except the one in the `Map` resource, plus the links between them.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Levels {
+    pub parked_maps: HashMap<i32, Map>,
+    pub links: Vec<LevelLink>,
+    /// Where the player last arrived, so standing on the arrival link doesn't bounce them back.
+    pub arrived_at: Option<Point>,
+}
+
+impl Levels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_generated(&self, depth: i32) -> bool {
+        self.parked_maps.contains_key(&depth)
+    }
+
+    pub fn link_at(&self, depth: i32, position: Point) -> Option<LevelLink> {
+        self.links
+            .iter()
+            .find(|link| link.depth == depth && link.position == position)
+            .cloned()
+    }
+
+    /// The tile on `depth` that leads back to `from`, which is where travellers from `from` arrive.
+    pub fn arrival(&self, depth: i32, from: i32) -> Option<Point> {
+        self.links
+            .iter()
+            .find(|link| link.depth == depth && link.target == from)
+            .map(|link| link.position)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::component::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const SALE_IMPACT: f32 = 0.9;
+const RECOVERY: f32 = 0.05;
+const OVERNIGHT_RECOVERY: f32 = 0.25;
+const MIN_DEMAND: f32 = 0.5;
+
+// Demand multipliers keyed by prototype id, since different items can share a display name.
+// Selling an item saturates the market for it, while everything else drifts back towards its
+// usual price, and the whole market recovers a good deal more overnight.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MarketDemand {
+    demand: HashMap<String, f32>,
+}
+
+impl MarketDemand {
+    pub fn new() -> Self {
+        Self {
+            demand: HashMap::new(),
+        }
+    }
+
+    pub fn multiplier(&self, kind: &str) -> f32 {
+        self.demand.get(kind).cloned().unwrap_or(1.0)
+    }
+
+    pub fn price(&self, kind: &str, value: &Value) -> u32 {
+        let price = value.base as f32 * value.rarity.multiplier() * self.multiplier(kind);
+        (price.round() as u32).max(1)
+    }
+
+    pub fn record_sale(&mut self, kind: &str) {
+        for (other, demand) in self.demand.iter_mut() {
+            if other != kind {
+                *demand += (1.0 - *demand) * RECOVERY;
+            }
+        }
+        let demand = self.demand.entry(kind.to_string()).or_insert(1.0);
+        *demand = (*demand * SALE_IMPACT).max(MIN_DEMAND);
+    }
+
+    pub fn next_day(&mut self) {
+        for demand in self.demand.values_mut() {
+            *demand += (1.0 - *demand) * OVERNIGHT_RECOVERY;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Rarity;
+
+    #[test]
+    fn test_sales_lower_price() {
+        let mut market = MarketDemand::new();
+        let value = Value {
+            base: 12,
+            rarity: Rarity::Uncommon,
+        };
+        assert_eq!(market.price("orange", &value), 18);
+        market.record_sale("orange");
+        assert_eq!(market.price("orange", &value), 16);
+        market.record_sale("cherries");
+        assert!(market.multiplier("orange") > 0.9);
+        let saturated = market.multiplier("cherries");
+        market.next_day();
+        assert!(market.multiplier("cherries") > saturated);
+        assert!(market.multiplier("cherries") < 1.0);
+    }
+}
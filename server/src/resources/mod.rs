@@ -0,0 +1,10 @@
+pub mod trade_handler;
+pub mod action_queue;
+pub mod message_queue;
+pub mod negotiation;
+pub mod network_registry;
+pub mod market;
+pub mod shop_day;
+pub mod movement_rules;
+pub mod levels;
+pub mod customer_schedule;
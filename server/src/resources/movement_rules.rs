@@ -0,0 +1,105 @@
+use crate::geom::Point;
+use crate::map::Map;
+use serde::{Deserialize, Serialize};
+
+const ORTHOGONAL: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+const ALL_DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (0, -1),
+    (1, 0),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    FourWay,
+    EightWay,
+    /// Diagonal steps are only allowed when both orthogonal tiles they pass are open.
+    EightWayNoCornerCutting,
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::FourWay
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MovementRules {
+    pub topology: Topology,
+}
+
+impl MovementRules {
+    pub fn new(topology: Topology) -> Self {
+        Self { topology }
+    }
+
+    pub fn offsets(&self) -> &'static [(i32, i32)] {
+        match self.topology {
+            Topology::FourWay => &ORTHOGONAL,
+            Topology::EightWay | Topology::EightWayNoCornerCutting => &ALL_DIRECTIONS,
+        }
+    }
+
+    /// Number of steps between two points with nothing in the way.
+    pub fn distance(&self, a: Point, b: Point) -> i32 {
+        let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+        match self.topology {
+            Topology::FourWay => dx + dy,
+            Topology::EightWay | Topology::EightWayNoCornerCutting => dx.max(dy),
+        }
+    }
+
+    /// Whether a single step from `from` to `to` is legal on `map`, ignoring entities.
+    pub fn can_step(&self, map: &Map, from: Point, to: Point) -> bool {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        if !self.offsets().contains(&(dx, dy)) || map.is_blocked(to) {
+            return false;
+        }
+        match self.topology {
+            Topology::EightWayNoCornerCutting if dx != 0 && dy != 0 => {
+                !map.is_blocked(Point::new(from.x + dx, from.y))
+                    && !map.is_blocked(Point::new(from.x, from.y + dy))
+            }
+            _ => true,
+        }
+    }
+
+    pub fn neighbours(&self, map: &Map, from: Point) -> Vec<Point> {
+        self.offsets()
+            .iter()
+            .map(|(dx, dy)| Point::new(from.x + dx, from.y + dy))
+            .filter(|to| self.can_step(map, from, *to))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+
+    #[test]
+    fn test_corner_cutting() {
+        // ###
+        // #.#
+        // ..#
+        let mut map = Map::new((3, 3), 0);
+        map.set_type(Point::new(1, 1), TileType::Floor);
+        map.set_type(Point::new(0, 2), TileType::Floor);
+        map.set_type(Point::new(1, 2), TileType::Floor);
+        map.refresh_blocked();
+        let from = Point::new(0, 2);
+        let to = Point::new(1, 1);
+
+        assert!(!MovementRules::new(Topology::FourWay).can_step(&map, from, to));
+        assert!(MovementRules::new(Topology::EightWay).can_step(&map, from, to));
+        assert!(!MovementRules::new(Topology::EightWayNoCornerCutting).can_step(&map, from, to));
+        assert!(!MovementRules::new(Topology::EightWay).can_step(&map, from, Point::new(-1, 3)));
+    }
+}
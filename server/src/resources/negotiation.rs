@@ -0,0 +1,119 @@
+use crate::resources::trade_handler::TradeState;
+use std::collections::HashMap;
+
+pub struct NegotiationContext {
+    pub base_value: u32,
+    pub patience: u32,
+    pub rounds: u32,
+    pub asking: Option<u32>,
+}
+
+pub trait NegotiationPolicy: Send + Sync {
+    fn respond(&self, context: &NegotiationContext) -> TradeState;
+}
+
+fn scaled(value: u32, ratio: f32) -> u32 {
+    (value as f32 * ratio).round() as u32
+}
+
+// How much the buyer is prepared to pay, creeping from `open` to `limit` as their patience runs out.
+fn willingness(context: &NegotiationContext, open: f32, limit: f32) -> u32 {
+    let progress = if context.patience == 0 {
+        1.0
+    } else {
+        context.rounds.min(context.patience) as f32 / context.patience as f32
+    };
+    scaled(context.base_value, open + (limit - open) * progress).max(1)
+}
+
+pub struct Haggler;
+
+impl NegotiationPolicy for Haggler {
+    fn respond(&self, context: &NegotiationContext) -> TradeState {
+        if context.rounds > context.patience {
+            return TradeState::Rejected;
+        }
+        let willing = willingness(context, 0.5, 1.2);
+        match context.asking {
+            Some(asking) if asking <= willing => TradeState::Accepted,
+            _ => TradeState::Offer(willing),
+        }
+    }
+}
+
+pub struct Impulsive;
+
+impl NegotiationPolicy for Impulsive {
+    fn respond(&self, context: &NegotiationContext) -> TradeState {
+        match context.asking {
+            Some(asking) if asking <= scaled(context.base_value, 1.5) => TradeState::Accepted,
+            Some(_) if context.rounds > context.patience / 2 => TradeState::Rejected,
+            _ => TradeState::Offer(context.base_value.max(1)),
+        }
+    }
+}
+
+pub struct Cheapskate;
+
+impl NegotiationPolicy for Cheapskate {
+    fn respond(&self, context: &NegotiationContext) -> TradeState {
+        match context.asking {
+            Some(asking) if asking > scaled(context.base_value, 1.2) => TradeState::Rejected,
+            _ if context.rounds > context.patience => TradeState::Rejected,
+            Some(asking) if asking <= willingness(context, 0.3, 0.9) => TradeState::Accepted,
+            _ => TradeState::Offer(willingness(context, 0.3, 0.9)),
+        }
+    }
+}
+
+pub struct NegotiationPolicies {
+    policies: HashMap<String, Box<dyn NegotiationPolicy>>,
+}
+
+impl NegotiationPolicies {
+    pub fn new() -> Self {
+        let mut policies = Self {
+            policies: HashMap::new(),
+        };
+        policies.register("haggler", Haggler);
+        policies.register("impulsive", Impulsive);
+        policies.register("cheapskate", Cheapskate);
+        policies
+    }
+
+    pub fn register(&mut self, name: &str, policy: impl NegotiationPolicy + 'static) {
+        self.policies.insert(name.to_string(), Box::new(policy));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn NegotiationPolicy> {
+        self.policies.get(name).map(|policy| policy.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(rounds: u32, asking: Option<u32>) -> NegotiationContext {
+        NegotiationContext {
+            base_value: 100,
+            patience: 4,
+            rounds,
+            asking,
+        }
+    }
+
+    #[test]
+    fn test_haggler_raises_offer_until_patience_runs_out() {
+        assert_eq!(Haggler.respond(&context(0, None)), TradeState::Offer(50));
+        assert_eq!(Haggler.respond(&context(2, Some(150))), TradeState::Offer(85));
+        assert_eq!(Haggler.respond(&context(4, Some(115))), TradeState::Accepted);
+        assert_eq!(Haggler.respond(&context(5, Some(150))), TradeState::Rejected);
+    }
+
+    #[test]
+    fn test_cheapskate_walks_away_from_high_prices() {
+        assert_eq!(Cheapskate.respond(&context(1, Some(130))), TradeState::Rejected);
+        assert_eq!(Impulsive.respond(&context(1, Some(130))), TradeState::Accepted);
+    }
+}
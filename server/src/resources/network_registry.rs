@@ -0,0 +1,66 @@
+use crate::component::NetworkId;
+use legion::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetworkIdError {
+    /// The id was never handed out by this registry.
+    Unknown(u64),
+    /// The id used to belong to an entity that has since been deleted.
+    Stale(u64),
+}
+
+pub struct NetworkRegistry {
+    next_id: u64,
+    entities: HashMap<NetworkId, Entity>,
+    ids: HashMap<Entity, NetworkId>,
+}
+
+impl NetworkRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            entities: HashMap::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, entity: Entity) -> NetworkId {
+        if let Some(id) = self.ids.get(&entity) {
+            return *id;
+        }
+        let id = NetworkId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, entity);
+        self.ids.insert(entity, id);
+        id
+    }
+
+    pub fn id_of(&self, entity: Entity) -> Option<NetworkId> {
+        self.ids.get(&entity).cloned()
+    }
+
+    pub fn resolve(&self, id: NetworkId) -> Result<Entity, NetworkIdError> {
+        match self.entities.get(&id) {
+            Some(entity) => Ok(*entity),
+            None if id.0 < self.next_id => Err(NetworkIdError::Stale(id.0)),
+            None => Err(NetworkIdError::Unknown(id.0)),
+        }
+    }
+
+    /// Drops every entity for which `alive` returns false. Their ids are never reused.
+    pub fn retain<F: Fn(Entity) -> bool>(&mut self, alive: F) {
+        let dead: Vec<Entity> = self.ids.keys().filter(|entity| !alive(**entity)).cloned().collect();
+        for entity in dead {
+            if let Some(id) = self.ids.remove(&entity) {
+                self.entities.remove(&id);
+            }
+        }
+    }
+
+    /// Forgets every mapping while keeping the counter, so ids from before a reload read as stale.
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.ids.clear();
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ledger {
+    pub day: u32,
+    pub sales: u32,
+    pub revenue: u32,
+    pub failed_sales: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShopDay {
+    pub day: u32,
+    pub closes_at_round: u64,
+    pub ledger: Ledger,
+    /// The prefab id of the shop, which picks its stock table.
+    #[serde(default)]
+    pub shop: String,
+}
+
+impl ShopDay {
+    pub fn new() -> Self {
+        Self {
+            day: 1,
+            closes_at_round: 0,
+            ledger: Ledger {
+                day: 1,
+                ..Ledger::default()
+            },
+            shop: String::new(),
+        }
+    }
+
+    pub fn record_sale(&mut self, value: u32) {
+        self.ledger.sales += 1;
+        self.ledger.revenue += value;
+    }
+
+    pub fn record_failed_sale(&mut self) {
+        self.ledger.failed_sales += 1;
+    }
+
+    /// Closes the books on the current day and returns its ledger.
+    pub fn next_day(&mut self) -> Ledger {
+        self.day += 1;
+        let next = Ledger {
+            day: self.day,
+            ..Ledger::default()
+        };
+        std::mem::replace(&mut self.ledger, next)
+    }
+}
@@ -1,6 +1,7 @@
 use legion::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::server::resources::trade_handler::TradeState::{Rejected, Final};
+use crate::resources::trade_handler::TradeState::{Rejected, Final};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TradeMessage {
@@ -9,12 +10,12 @@ pub struct TradeMessage {
     pub state_change: TradeState
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TradeRequest {
-    pub(crate) id: u64
+    pub id: u64
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TradeState {
     Pending,
     Start,
@@ -29,10 +30,13 @@ pub enum TradeState {
 pub struct Trade {
     pub request: TradeRequest,
     pub target: Entity,
+    /// Units of the `target` stack on the table; offers are for all of them together.
+    pub count: u32,
     pub buyer: Entity,
     pub seller: Entity,
     pub last_response: Entity,
-    pub trade_state: TradeState
+    pub trade_state: TradeState,
+    pub rounds: u32
 }
 
 pub struct TradeHandler {
@@ -50,17 +54,19 @@ impl TradeHandler {
         }
     }
 
-    pub fn start(&mut self, target: Entity, buyer: Entity, seller: Entity, origin: Entity) -> TradeRequest {
+    pub fn start(&mut self, target: Entity, count: u32, buyer: Entity, seller: Entity, origin: Entity) -> TradeRequest {
         let request = TradeRequest{
             id: self.increment()
         };
         let trade = Trade {
             request,
             target,
+            count,
             buyer,
             seller,
             last_response: origin,
-            trade_state: TradeState::Pending
+            trade_state: TradeState::Pending,
+            rounds: 0
         };
         self.active_requests.insert(request, trade.clone());
         request
@@ -73,6 +79,26 @@ impl TradeHandler {
 
     }
 
+    pub fn restore(next_id: u64, active: Vec<Trade>, finished: Vec<Trade>) -> Self {
+        TradeHandler {
+            next_id,
+            active_requests: active.into_iter().map(|trade| (trade.request, trade)).collect(),
+            finished_requests: finished.into_iter().map(|trade| (trade.request, trade)).collect(),
+        }
+    }
+
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    pub fn active_trades(&self) -> impl Iterator<Item = &Trade> {
+        self.active_requests.values()
+    }
+
+    pub fn finished_trades(&self) -> impl Iterator<Item = &Trade> {
+        self.finished_requests.values()
+    }
+
     pub fn get_trade(&self, request: TradeRequest) -> Option<Trade> {
         self.active_requests.get(&request).map(
             |value| value.clone()
@@ -104,6 +130,7 @@ impl TradeHandler {
                         || (std::mem::discriminant(&TradeState::CounterOffer(1)) == std::mem::discriminant(&trade.trade_state)) {
                         trade.trade_state = TradeState::Offer(value);
                         trade.last_response = message.origin;
+                        trade.rounds += 1;
                         return Some(trade.clone())
                     }
                 }
@@ -115,6 +142,7 @@ impl TradeHandler {
                         || (std::mem::discriminant(&TradeState::Offer(1)) == std::mem::discriminant(&trade.trade_state)) {
                         trade.trade_state = TradeState::CounterOffer(value);
                         trade.last_response = message.origin;
+                        trade.rounds += 1;
                         return Some(trade.clone())
                     }
                 }
@@ -0,0 +1,5 @@
+use rand_pcg::Lcg64Xsh32;
+
+/// The generator behind every seeded roll. rand only promises `StdRng`'s output within one
+/// version, so games seed a named algorithm instead to keep a seed replaying the same game.
+pub type GameRng = Lcg64Xsh32;
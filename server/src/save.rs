@@ -0,0 +1,400 @@
+use crate::component::{
+    ActiveTurn, Customer, CustomerState, DisplayCabinet, Inventory, MovePlan, Name, Negotiator,
+    Parked, Player, Position, Priority, Prototype, Quantity, Renderable, TileBlocker, Tradeable,
+    Value, Viewshed, Wallet,
+};
+use crate::gamestate::RunState;
+use crate::geom::Point;
+use crate::map::Map;
+use crate::resources::action_queue::ActionQueue;
+use crate::resources::market::MarketDemand;
+use crate::resources::shop_day::ShopDay;
+use crate::resources::customer_schedule::CustomerSchedule;
+use crate::resources::movement_rules::MovementRules;
+use crate::resources::levels::Levels;
+use crate::resources::message_queue::MessageQueue;
+use crate::resources::trade_handler::{Trade, TradeHandler, TradeRequest, TradeState};
+use crate::systems::turn_system::PendingMoves;
+use legion::prelude::*;
+use crate::rng::GameRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub const SAVE_VERSION: u32 = 2;
+
+// Entities are written out by their position in the save file rather than by legion handle,
+// which only means something to the world that produced it.
+type SaveId = u64;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+    DanglingEntity(SaveId),
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Format(error)
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub rng: GameRng,
+    pub round: u64,
+    pub run_state: RunState,
+    pub map: Map,
+    pub entities: Vec<SavedEntity>,
+    pub next_trade_id: u64,
+    pub active_trades: Vec<SavedTrade>,
+    pub finished_trades: Vec<SavedTrade>,
+    pub market: MarketDemand,
+    pub shop_day: ShopDay,
+    pub movement: MovementRules,
+    pub levels: Levels,
+    pub customer_schedule: CustomerSchedule,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedEntity {
+    pub id: SaveId,
+    pub player: bool,
+    pub display_cabinet: bool,
+    pub tile_blocker: bool,
+    pub position: Option<Position>,
+    pub renderable: Option<Renderable>,
+    pub name: Option<Name>,
+    pub tradeable: Option<Tradeable>,
+    pub active_turn: Option<ActiveTurn>,
+    pub priority: Option<Priority>,
+    pub inventory: Option<SavedInventory>,
+    pub customer: Option<SavedCustomer>,
+    pub negotiator: Option<Negotiator>,
+    pub wallet: Option<Wallet>,
+    pub value: Option<Value>,
+    pub viewshed: Option<Viewshed>,
+    pub move_plan: Option<MovePlan>,
+    pub parked: Option<Parked>,
+    pub prototype: Option<Prototype>,
+    pub quantity: Option<Quantity>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedInventory {
+    pub contents: Vec<SaveId>,
+    pub capacity: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedCustomer {
+    pub state: CustomerState,
+    pub target: Option<SaveId>,
+    pub exit: Point,
+    pub patience: u32,
+    pub stuck: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedTrade {
+    pub request: TradeRequest,
+    pub target: SaveId,
+    pub count: u32,
+    pub buyer: SaveId,
+    pub seller: SaveId,
+    pub last_response: SaveId,
+    pub trade_state: TradeState,
+    pub rounds: u32,
+}
+
+// Temporary marker that lets the loader allocate every entity before wiring up references.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Loading(SaveId);
+
+fn gather<T: Component>(world: &World, seen: &mut HashSet<Entity>, entities: &mut Vec<Entity>) {
+    let query = <Read<T>>::query();
+    for (entity, _) in query.iter_entities(world) {
+        if seen.insert(entity) {
+            entities.push(entity);
+        }
+    }
+}
+
+fn save_trade(trade: &Trade, ids: &HashMap<Entity, SaveId>) -> Option<SavedTrade> {
+    Some(SavedTrade {
+        request: trade.request,
+        target: *ids.get(&trade.target)?,
+        count: trade.count,
+        buyer: *ids.get(&trade.buyer)?,
+        seller: *ids.get(&trade.seller)?,
+        last_response: *ids.get(&trade.last_response)?,
+        trade_state: trade.trade_state,
+        rounds: trade.rounds,
+    })
+}
+
+fn load_trade(
+    trade: &SavedTrade,
+    lookup: &dyn Fn(SaveId) -> Result<Entity, SaveError>,
+) -> Result<Trade, SaveError> {
+    Ok(Trade {
+        request: trade.request,
+        target: lookup(trade.target)?,
+        count: trade.count,
+        buyer: lookup(trade.buyer)?,
+        seller: lookup(trade.seller)?,
+        last_response: lookup(trade.last_response)?,
+        trade_state: trade.trade_state,
+        rounds: trade.rounds,
+    })
+}
+
+pub fn capture(world: &World, resources: &Resources, run_state: RunState) -> SaveFile {
+    let mut seen = HashSet::new();
+    let mut entities = vec![];
+    gather::<Position>(world, &mut seen, &mut entities);
+    gather::<Renderable>(world, &mut seen, &mut entities);
+    gather::<Name>(world, &mut seen, &mut entities);
+    gather::<Tradeable>(world, &mut seen, &mut entities);
+    gather::<ActiveTurn>(world, &mut seen, &mut entities);
+    gather::<TileBlocker>(world, &mut seen, &mut entities);
+    gather::<Priority>(world, &mut seen, &mut entities);
+    gather::<Inventory>(world, &mut seen, &mut entities);
+    gather::<Customer>(world, &mut seen, &mut entities);
+    gather::<Negotiator>(world, &mut seen, &mut entities);
+    gather::<Wallet>(world, &mut seen, &mut entities);
+    gather::<Value>(world, &mut seen, &mut entities);
+    gather::<Viewshed>(world, &mut seen, &mut entities);
+    gather::<MovePlan>(world, &mut seen, &mut entities);
+    gather::<Parked>(world, &mut seen, &mut entities);
+    gather::<Prototype>(world, &mut seen, &mut entities);
+    gather::<Quantity>(world, &mut seen, &mut entities);
+
+    let ids: HashMap<Entity, SaveId> = entities
+        .iter()
+        .enumerate()
+        .map(|(index, entity)| (*entity, index as SaveId))
+        .collect();
+    let saved = entities
+        .iter()
+        .map(|entity| {
+            let entity = *entity;
+            SavedEntity {
+                id: ids[&entity],
+                player: world.get_tag::<Player>(entity).is_some(),
+                display_cabinet: world.get_tag::<DisplayCabinet>(entity).is_some(),
+                tile_blocker: world.get_component::<TileBlocker>(entity).is_some(),
+                position: world.get_component::<Position>(entity).map(|c| *c),
+                renderable: world.get_component::<Renderable>(entity).map(|c| *c),
+                name: world.get_component::<Name>(entity).map(|c| c.as_ref().clone()),
+                tradeable: world.get_component::<Tradeable>(entity).map(|c| *c),
+                active_turn: world.get_component::<ActiveTurn>(entity).map(|c| *c),
+                priority: world.get_component::<Priority>(entity).map(|c| *c),
+                inventory: world.get_component::<Inventory>(entity).map(|inventory| SavedInventory {
+                    contents: inventory.contents.iter().filter_map(|item| ids.get(item).cloned()).collect(),
+                    capacity: inventory.capacity,
+                }),
+                customer: world.get_component::<Customer>(entity).map(|customer| SavedCustomer {
+                    state: customer.state,
+                    target: customer.target.and_then(|target| ids.get(&target).cloned()),
+                    exit: customer.exit,
+                    patience: customer.patience,
+                    stuck: customer.stuck,
+                }),
+                negotiator: world.get_component::<Negotiator>(entity).map(|c| c.as_ref().clone()),
+                wallet: world.get_component::<Wallet>(entity).map(|c| *c),
+                value: world.get_component::<Value>(entity).map(|c| *c),
+                viewshed: world.get_component::<Viewshed>(entity).map(|c| c.as_ref().clone()),
+                move_plan: world.get_component::<MovePlan>(entity).map(|c| c.as_ref().clone()),
+                parked: world.get_component::<Parked>(entity).map(|c| *c),
+                prototype: world.get_component::<Prototype>(entity).map(|c| c.as_ref().clone()),
+                quantity: world.get_component::<Quantity>(entity).map(|c| *c),
+            }
+        })
+        .collect();
+
+    let trade_handler = resources.get::<TradeHandler>().unwrap();
+    SaveFile {
+        version: SAVE_VERSION,
+        // The generator's state rather than a draw from it, so saving doesn't change what
+        // happens next.
+        rng: resources.get::<GameRng>().unwrap().clone(),
+        round: resources.get::<PendingMoves>().unwrap().round(),
+        run_state,
+        map: resources.get::<Map>().unwrap().clone(),
+        entities: saved,
+        next_trade_id: trade_handler.next_id(),
+        active_trades: trade_handler.active_trades().filter_map(|trade| save_trade(trade, &ids)).collect(),
+        // Finished trades can name customers that have since left; those are simply forgotten.
+        finished_trades: trade_handler.finished_trades().filter_map(|trade| save_trade(trade, &ids)).collect(),
+        market: resources.get::<MarketDemand>().unwrap().clone(),
+        shop_day: resources.get::<ShopDay>().unwrap().clone(),
+        movement: *resources.get::<MovementRules>().unwrap(),
+        levels: resources.get::<Levels>().unwrap().clone(),
+        customer_schedule: *resources.get::<CustomerSchedule>().unwrap(),
+    }
+}
+
+/// Rebuilds a saved game into `world`, which should be freshly created. Resources are only
+/// replaced once every entity reference in the file has been resolved.
+pub fn restore(file: SaveFile, world: &mut World, resources: &mut Resources) -> Result<RunState, SaveError> {
+    if file.version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(file.version));
+    }
+    let markers: Vec<(Loading,)> = file.entities.iter().map(|saved| (Loading(saved.id),)).collect();
+    let spawned = world.insert((), markers).to_vec();
+    let entities: HashMap<SaveId, Entity> = file
+        .entities
+        .iter()
+        .map(|saved| saved.id)
+        .zip(spawned.into_iter())
+        .collect();
+    let lookup = |id: SaveId| entities.get(&id).cloned().ok_or(SaveError::DanglingEntity(id));
+
+    let mut buffer = CommandBuffer::new(world);
+    for saved in &file.entities {
+        let entity = lookup(saved.id)?;
+        if saved.player {
+            buffer.add_tag(entity, Player);
+        }
+        if saved.display_cabinet {
+            buffer.add_tag(entity, DisplayCabinet);
+        }
+        if saved.tile_blocker {
+            buffer.add_component(entity, TileBlocker);
+        }
+        if let Some(position) = saved.position {
+            buffer.add_component(entity, position);
+        }
+        if let Some(renderable) = saved.renderable {
+            buffer.add_component(entity, renderable);
+        }
+        if let Some(name) = &saved.name {
+            buffer.add_component(entity, name.clone());
+        }
+        if let Some(tradeable) = saved.tradeable {
+            buffer.add_component(entity, tradeable);
+        }
+        if let Some(active_turn) = saved.active_turn {
+            buffer.add_component(entity, active_turn);
+        }
+        if let Some(priority) = saved.priority {
+            buffer.add_component(entity, priority);
+        }
+        if let Some(inventory) = &saved.inventory {
+            let contents = inventory
+                .contents
+                .iter()
+                .map(|item| lookup(*item))
+                .collect::<Result<Vec<_>, _>>()?;
+            buffer.add_component(
+                entity,
+                Inventory {
+                    contents,
+                    capacity: inventory.capacity,
+                },
+            );
+        }
+        if let Some(customer) = &saved.customer {
+            let target = match customer.target {
+                Some(target) => Some(lookup(target)?),
+                None => None,
+            };
+            buffer.add_component(
+                entity,
+                Customer {
+                    state: customer.state,
+                    target,
+                    exit: customer.exit,
+                    patience: customer.patience,
+                    stuck: customer.stuck,
+                },
+            );
+        }
+        if let Some(negotiator) = &saved.negotiator {
+            buffer.add_component(entity, negotiator.clone());
+        }
+        if let Some(wallet) = saved.wallet {
+            buffer.add_component(entity, wallet);
+        }
+        if let Some(value) = saved.value {
+            buffer.add_component(entity, value);
+        }
+        if let Some(viewshed) = &saved.viewshed {
+            buffer.add_component(entity, viewshed.clone());
+        }
+        if let Some(move_plan) = &saved.move_plan {
+            buffer.add_component(entity, move_plan.clone());
+        }
+        if let Some(parked) = saved.parked {
+            buffer.add_component(entity, parked);
+        }
+        if let Some(prototype) = &saved.prototype {
+            buffer.add_component(entity, prototype.clone());
+        }
+        if let Some(quantity) = saved.quantity {
+            buffer.add_component(entity, quantity);
+        }
+        buffer.remove_component::<Loading>(entity);
+    }
+    let active = file
+        .active_trades
+        .iter()
+        .map(|trade| load_trade(trade, &lookup))
+        .collect::<Result<Vec<_>, _>>()?;
+    let finished = file
+        .finished_trades
+        .iter()
+        .map(|trade| load_trade(trade, &lookup))
+        .collect::<Result<Vec<_>, _>>()?;
+    buffer.write(world);
+
+    let mut map = file.map;
+    map.tile_content = vec![None; map.tiles.len()];
+    resources.insert(map);
+    resources.insert(TradeHandler::restore(file.next_trade_id, active, finished));
+    resources.insert(PendingMoves::resume(file.round));
+    resources.insert(file.rng);
+    resources.insert(MessageQueue::new());
+    resources.insert(ActionQueue::new());
+    resources.insert(file.market);
+    resources.insert(file.shop_day);
+    resources.insert(file.movement);
+    resources.insert(file.customer_schedule);
+    let mut levels = file.levels;
+    for map in levels.parked_maps.values_mut() {
+        map.tile_content = vec![None; map.tiles.len()];
+    }
+    resources.insert(levels);
+    Ok(file.run_state)
+}
+
+pub fn write<P: AsRef<Path>>(path: P, file: &SaveFile) -> Result<(), SaveError> {
+    let raw = serde_json::to_string_pretty(file)?;
+    fs::write(path, raw)?;
+    Ok(())
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> Result<SaveFile, SaveError> {
+    let raw = fs::read_to_string(path)?;
+    let header: Header = serde_json::from_str(&raw)?;
+    if header.version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(header.version));
+    }
+    Ok(serde_json::from_str(&raw)?)
+}
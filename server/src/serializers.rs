@@ -0,0 +1,640 @@
+use crate::color::{Color, ColorParseError};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+pub mod components;
+mod templates;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Data {
+    builder: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EntityBuilder {
+    id: String,
+    renderable: Renderable,
+    name: String,
+    display_cabinet: Option<bool>,
+    inventory: Option<Inventory>,
+    /// Everything else, built by whatever is registered under its key.
+    #[serde(flatten)]
+    components: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Inventory {
+    contents: Vec<String>,
+    capacity: u8,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Renderable {
+    pub glyph: GlyphData,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GlyphData {
+    pub ch: char,
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    pub render_order: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Priority {
+    pub value: u8,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Customer {
+    pub patience: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Value {
+    pub base: u32,
+    pub rarity: Option<crate::component::Rarity>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Wallet {
+    pub gold: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Viewshed {
+    pub range: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Negotiator {
+    pub policy: String,
+    pub patience: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Stack {
+    pub max: u32,
+}
+
+#[derive(Debug)]
+pub enum FactoryError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    Invalid(Vec<SchemaError>),
+}
+
+impl From<std::io::Error> for FactoryError {
+    fn from(error: std::io::Error) -> Self {
+        FactoryError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for FactoryError {
+    fn from(error: serde_json::Error) -> Self {
+        FactoryError::Format(error)
+    }
+}
+
+impl fmt::Display for FactoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FactoryError::Io(error) => write!(f, "couldn't read data file: {}", error),
+            FactoryError::Format(error) => write!(f, "data file isn't valid json: {}", error),
+            FactoryError::Invalid(errors) => {
+                write!(f, "{} problem(s) in data file:", errors.len())?;
+                for error in errors.iter() {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for FactoryError {}
+
+/// One problem in an entity file. `path` points into the json, e.g. `builder[3].inventory.contents[0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: String,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    DuplicateId(String),
+    UnknownId(String),
+    NestedInventory(String),
+    OverCapacity { count: usize, capacity: u8 },
+    BadGlyph(char),
+    BadColor(ColorParseError),
+    Malformed(String),
+    CyclicTemplate(String),
+    AbstractTemplate(String),
+    UnknownComponent(String),
+    BadComponent(String),
+    MissingId(String),
+    DuplicateTable(String),
+    NeverRolls,
+    UnknownTile(char),
+    StartingPositions(usize),
+    Empty,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.problem {
+            Problem::DuplicateId(id) => write!(f, "{:?} is already defined", id),
+            Problem::UnknownId(id) => write!(f, "no entity has the id {:?}", id),
+            Problem::NestedInventory(id) => {
+                write!(f, "{:?} has an inventory, so it can't be stored in one", id)
+            }
+            Problem::OverCapacity { count, capacity } => {
+                write!(f, "{} items don't fit in a capacity of {}", count, capacity)
+            }
+            Problem::BadGlyph(ch) => write!(f, "{:?} can't be drawn as a glyph", ch),
+            Problem::BadColor(error) => write!(f, "{}", error),
+            Problem::Malformed(error) => write!(f, "{}", error),
+            Problem::CyclicTemplate(id) => write!(f, "{:?} ends up extending itself", id),
+            Problem::AbstractTemplate(id) => write!(f, "{:?} is abstract and can't be built", id),
+            Problem::UnknownComponent(key) => write!(f, "no component is registered as {:?}", key),
+            Problem::BadComponent(error) => write!(f, "{}", error),
+            Problem::MissingId(id) => write!(f, "{:?} is built by the game, so it must be defined", id),
+            Problem::DuplicateTable(key) => write!(f, "another table already stocks {}", key),
+            Problem::NeverRolls => write!(f, "no entry has any weight, so nothing is ever rolled"),
+            Problem::UnknownTile(ch) => write!(f, "{:?} is neither a tile nor in the legend", ch),
+            Problem::StartingPositions(count) => {
+                write!(f, "needs exactly one starting position, not {}", count)
+            }
+            Problem::Empty => write!(f, "nothing is defined"),
+        }
+    }
+}
+
+impl EntityBuilder {
+    /// Checks everything that doesn't need the component registry.
+    fn validate(
+        &self,
+        path: &str,
+        builders: &HashMap<&str, &EntityBuilder>,
+        abstract_ids: &HashSet<&str>,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let mut error = |path: String, problem| errors.push(SchemaError { path, problem });
+        let glyph = &self.renderable.glyph;
+        if glyph.ch.is_whitespace() || glyph.ch.is_control() {
+            error(format!("{}.renderable.glyph.ch", path), Problem::BadGlyph(glyph.ch));
+        }
+        for (field, color) in [("foreground", &glyph.foreground), ("background", &glyph.background)].iter() {
+            if let Some(color) = color {
+                if let Err(parse_error) = color.parse::<Color>() {
+                    error(
+                        format!("{}.renderable.glyph.{}", path, field),
+                        Problem::BadColor(parse_error),
+                    );
+                }
+            }
+        }
+
+        if let Some(inventory) = &self.inventory {
+            if inventory.contents.len() > inventory.capacity as usize {
+                error(
+                    format!("{}.inventory.contents", path),
+                    Problem::OverCapacity {
+                        count: inventory.contents.len(),
+                        capacity: inventory.capacity,
+                    },
+                );
+            }
+            for (slot, item) in inventory.contents.iter().enumerate() {
+                let item_path = format!("{}.inventory.contents[{}]", path, slot);
+                match builders.get(item.as_str()) {
+                    None if abstract_ids.contains(item.as_str()) => {
+                        error(item_path, Problem::AbstractTemplate(item.clone()))
+                    }
+                    None => error(item_path, Problem::UnknownId(item.clone())),
+                    Some(item_builder) if item_builder.inventory.is_some() => {
+                        error(item_path, Problem::NestedInventory(item.clone()))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+}
+
+pub mod entity_factory {
+    use super::components::{Attach, ComponentRegistry};
+    use super::{templates, Data, EntityBuilder, FactoryError, Problem, SchemaError};
+    use crate::color::Color;
+    use crate::{component, geom::Point, glyph::Glyph};
+    use legion::prelude::{CommandBuffer, Entity};
+    use serde_json::from_str;
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::SystemTime;
+
+    pub const DEFAULT_PATH: &str = "entities.json";
+
+    struct Template {
+        builder: EntityBuilder,
+        components: Vec<Attach>,
+    }
+
+    /// The file a factory was loaded from, for reloading.
+    struct Source {
+        path: PathBuf,
+        modified: Option<SystemTime>,
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    fn parse_templates(
+        raw_string: &str,
+        components: &ComponentRegistry,
+    ) -> Result<HashMap<String, Template>, FactoryError> {
+        let data: Data = from_str(raw_string)?;
+        let mut errors = vec![vec![]; data.builder.len()];
+        let resolved = templates::resolve(&data.builder, &mut errors);
+
+        let mut builders = vec![];
+        let mut abstract_ids = HashSet::new();
+        for entry in resolved.into_iter() {
+            if entry.is_abstract {
+                abstract_ids.insert(data.builder[entry.index]["id"].as_str().unwrap_or_default());
+                continue;
+            }
+            match serde_json::from_value::<EntityBuilder>(entry.value) {
+                Ok(builder) => builders.push((entry.index, builder)),
+                Err(error) => errors[entry.index].push(SchemaError {
+                    path: format!("builder[{}]", entry.index),
+                    problem: Problem::Malformed(error.to_string()),
+                }),
+            }
+        }
+
+        let by_id: HashMap<&str, &EntityBuilder> = builders
+            .iter()
+            .map(|(_, builder)| (builder.id.as_str(), builder))
+            .collect();
+        let mut templates = HashMap::new();
+        for (index, builder) in builders.iter() {
+            let path = format!("builder[{}]", index);
+            let errors = &mut errors[*index];
+            builder.validate(&path, &by_id, &abstract_ids, errors);
+            let mut attach = vec![];
+            for (key, value) in builder.components.iter() {
+                let key_path = format!("{}.{}", path, key);
+                match components.load(key, value) {
+                    None => errors.push(SchemaError {
+                        path: key_path,
+                        problem: Problem::UnknownComponent(key.clone()),
+                    }),
+                    Some(Err(error)) => errors.push(SchemaError {
+                        path: key_path,
+                        problem: Problem::BadComponent(error.to_string()),
+                    }),
+                    Some(Ok(component)) => attach.push(component),
+                }
+            }
+            templates.insert(
+                builder.id.clone(),
+                Template {
+                    builder: builder.clone(),
+                    components: attach,
+                },
+            );
+        }
+
+        let errors: Vec<SchemaError> = errors.into_iter().flatten().collect();
+        if !errors.is_empty() {
+            return Err(FactoryError::Invalid(errors));
+        }
+        Ok(templates)
+    }
+
+    pub struct EntityFactory {
+        templates: HashMap<String, Template>,
+        components: ComponentRegistry,
+        source: Option<Source>,
+    }
+
+    impl EntityFactory {
+        pub fn load() -> Self {
+            Self::load_from(DEFAULT_PATH)
+        }
+
+        pub fn load_from<P: AsRef<Path>>(path: P) -> Self {
+            Self::try_load_from(path).unwrap_or_else(|error| panic!("{}", error))
+        }
+
+        pub fn try_load_from<P: AsRef<Path>>(path: P) -> Result<Self, FactoryError> {
+            let path = path.as_ref();
+            let modified = modified(path);
+            let raw_string = fs::read_to_string(path)?;
+            let mut factory = Self::parse(&raw_string)?;
+            factory.source = Some(Source {
+                path: path.to_path_buf(),
+                modified,
+            });
+            Ok(factory)
+        }
+
+        pub fn parse(raw_string: &str) -> Result<Self, FactoryError> {
+            Self::parse_with(raw_string, ComponentRegistry::default())
+        }
+
+        /// Parses and validates an entity file, reporting every problem rather than the first.
+        /// Keys that aren't part of `EntityBuilder` are built by `components`.
+        pub fn parse_with(raw_string: &str, components: ComponentRegistry) -> Result<Self, FactoryError> {
+            let templates = parse_templates(raw_string, &components)?;
+            Ok(Self {
+                templates,
+                components,
+                source: None,
+            })
+        }
+
+        /// Re-reads the file this factory was loaded from. The new definitions are only kept if
+        /// the file is valid and they pass `check`, e.g. still define every id something else
+        /// refers to; otherwise the old ones stay.
+        pub fn reload<F>(&mut self, check: F) -> Result<(), FactoryError>
+        where
+            F: FnOnce(&EntityFactory) -> Result<(), FactoryError>,
+        {
+            let source = match &mut self.source {
+                Some(source) => source,
+                None => return Ok(()),
+            };
+            // Remember the attempt even if it fails, so a broken file is only reported once.
+            source.modified = modified(&source.path);
+            let raw_string = fs::read_to_string(&source.path)?;
+            let templates = parse_templates(&raw_string, &self.components)?;
+            let previous = std::mem::replace(&mut self.templates, templates);
+            if let Err(error) = check(self) {
+                self.templates = previous;
+                return Err(error);
+            }
+            Ok(())
+        }
+
+        /// Whether the source file has changed since it was last read.
+        pub fn is_stale(&self) -> bool {
+            self.source
+                .as_ref()
+                .map_or(false, |source| modified(&source.path) != source.modified)
+        }
+
+        pub fn validate<P: AsRef<Path>>(path: P) -> Result<(), FactoryError> {
+            Self::try_load_from(path).map(|_| ())
+        }
+
+        /// Whether `id` can be built. Abstract templates can't.
+        pub fn contains(&self, id: &str) -> bool {
+            self.templates.contains_key(id)
+        }
+
+        // Colours are validated on load, so a failed parse here can't happen.
+        fn deserialize_color(color: &Option<String>) -> Option<Color> {
+            color.as_ref().and_then(|color| color.parse().ok())
+        }
+
+        pub fn renderable(&self, id: &str) -> Option<component::Renderable> {
+            let glyph = &self.templates.get(id)?.builder.renderable.glyph;
+            Some(component::Renderable {
+                glyph: Glyph {
+                    ch: glyph.ch,
+                    foreground: Self::deserialize_color(&glyph.foreground),
+                    background: Self::deserialize_color(&glyph.background),
+                    render_order: glyph.render_order,
+                },
+            })
+        }
+
+        /// Whether `id` is built with an inventory, which display cabinets always are.
+        pub fn has_inventory(&self, id: &str) -> bool {
+            self.templates.get(id).map_or(false, |template| {
+                template.builder.inventory.is_some() || template.builder.display_cabinet == Some(true)
+            })
+        }
+
+        pub fn name(&self, id: &str) -> Option<component::Name> {
+            let builder = &self.templates.get(id)?.builder;
+            Some(component::Name {
+                name: builder.name.clone(),
+            })
+        }
+
+        /// Whether every id in `ids` can be built. The server builds some entities by id rather
+        /// than from data, so any file it loads has to define them.
+        pub fn check_defines(&self, ids: &[&str]) -> Result<(), FactoryError> {
+            let errors: Vec<SchemaError> = ids
+                .iter()
+                .filter(|id| !self.contains(id))
+                .map(|id| SchemaError {
+                    path: "builder".to_string(),
+                    problem: Problem::MissingId(id.to_string()),
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(FactoryError::Invalid(errors));
+            }
+            Ok(())
+        }
+
+        /// Builds `id` into `buffer`, or returns `None` if no entity has that id.
+        pub fn build(
+            &self,
+            id: &str,
+            position: Option<Point>,
+            buffer: &mut CommandBuffer,
+        ) -> Option<Entity> {
+            let template = self.templates.get(id)?;
+            let options = &template.builder;
+            let builder = buffer.start_entity();
+            let builder = builder
+                .with_component(self.renderable(id).unwrap())
+                .with_component(self.name(id).unwrap())
+                .with_component(component::Prototype { id: id.to_string() })
+                .with_component(component::TileBlocker);
+
+            let entity = builder.build();
+
+            if let Some(position) = &position {
+                buffer.add_component(
+                    entity,
+                    component::Position {
+                        x: position.x,
+                        y: position.y,
+                    },
+                );
+            }
+            for attach in template.components.iter() {
+                attach(entity, position, buffer);
+            }
+
+            let mut has_inventory = false;
+            if let Some(inventory) = &options.inventory {
+                has_inventory = true;
+                let mut contents = vec![];
+                // Validation already rejected unknown, nested and overflowing contents.
+                for item in inventory.contents.iter() {
+                    contents.extend(self.build(item, None, buffer));
+                }
+
+                buffer.add_component(
+                    entity,
+                    component::Inventory {
+                        contents: contents,
+                        capacity: inventory.capacity,
+                    },
+                )
+            }
+
+            if let Some(display) = options.display_cabinet {
+                if display {
+                    if !has_inventory {
+                        buffer.add_component(
+                            entity,
+                            component::Inventory {
+                                contents: vec![],
+                                capacity: 1,
+                            },
+                        );
+                    }
+                    buffer.add_tag(entity, component::DisplayCabinet);
+                }
+            }
+
+            Some(entity)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entity_factory::EntityFactory;
+    use super::*;
+    use legion::prelude::*;
+
+    fn problems(raw: &str) -> Vec<SchemaError> {
+        match EntityFactory::parse(raw) {
+            Err(FactoryError::Invalid(errors)) => errors,
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => vec![],
+        }
+    }
+
+    #[test]
+    fn test_shipped_entities_are_valid() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../entities.json");
+        if let Err(error) = EntityFactory::validate(path) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let errors = problems(
+            r##"{ "builder": [
+                { "id": "crate", "name": "Crate", "renderable": { "glyph": { "ch": "c", "render_order": 0 } },
+                  "inventory": { "contents": ["apple", "shelf", "pear"], "capacity": 2 } },
+                { "id": "shelf", "name": "Shelf", "renderable": { "glyph": { "ch": " ", "render_order": 0 } },
+                  "inventory": { "contents": [], "capacity": 1 } },
+                { "id": "apple", "name": "Apple", "renderable": { "glyph": { "ch": "a", "foreground": "#zz0000", "render_order": 0 } } },
+                { "id": "apple", "name": "Apple", "renderable": { "glyph": { "ch": "a", "render_order": 0 } } }
+            ] }"##,
+        );
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "builder[0].inventory.contents",
+                "builder[0].inventory.contents[1]",
+                "builder[0].inventory.contents[2]",
+                "builder[1].renderable.glyph.ch",
+                "builder[2].renderable.glyph.foreground",
+                "builder[3].id",
+            ]
+        );
+        assert_eq!(errors[0].problem, Problem::OverCapacity { count: 3, capacity: 2 });
+        assert_eq!(errors[1].problem, Problem::NestedInventory("shelf".to_string()));
+        assert_eq!(errors[2].problem, Problem::UnknownId("pear".to_string()));
+        assert_eq!(errors[5].problem, Problem::DuplicateId("apple".to_string()));
+    }
+
+    const TEMPLATES: &str = r##"{ "builder": [
+        { "id": "produce", "abstract": true, "renderable": { "glyph": { "ch": "p", "render_order": 3 } },
+          "value": { "base": 5, "rarity": "common" } },
+        { "id": "fruit", "extends": "produce", "abstract": true, "tradeable": true },
+        { "id": "apple", "extends": "fruit", "name": "Apple", "value": { "base": 7 },
+          "renderable": { "glyph": { "ch": "a" } } }
+    ] }"##;
+
+    #[test]
+    fn test_templates_merge_parents() {
+        let factory = EntityFactory::parse(TEMPLATES).unwrap();
+        assert!(factory.contains("apple"));
+        assert!(!factory.contains("fruit"));
+
+        let universe = Universe::new();
+        let mut world = universe.create_world();
+        let mut buffer = CommandBuffer::new(&world);
+        let apple = factory.build("apple", None, &mut buffer).unwrap();
+        assert!(factory.build("fruit", None, &mut buffer).is_none());
+        buffer.write(&mut world);
+        let value = *world.get_component::<crate::component::Value>(apple).unwrap();
+        assert_eq!(value.base, 7);
+        assert_eq!(value.rarity, crate::component::Rarity::Common);
+        assert_eq!(world.get_component::<crate::component::Renderable>(apple).unwrap().glyph.render_order, 3);
+        assert!(world.get_component::<crate::component::Tradeable>(apple).is_some());
+    }
+
+    #[test]
+    fn test_template_problems() {
+        let errors = problems(
+            r##"{ "builder": [
+                { "id": "a", "extends": "b" },
+                { "id": "b", "extends": "a" },
+                { "id": "c", "extends": "missing" },
+                { "id": "d", "name": "D", "renderable": { "glyph": { "ch": "d", "render_order": 0 } },
+                  "sparkle": 3, "wallet": { "gold": "lots" } }
+            ] }"##,
+        );
+        let problems: Vec<&Problem> = errors.iter().map(|error| &error.problem).collect();
+        assert_eq!(problems[0], &Problem::CyclicTemplate("a".to_string()));
+        assert_eq!(problems[1], &Problem::CyclicTemplate("b".to_string()));
+        assert_eq!(problems[2], &Problem::UnknownId("missing".to_string()));
+        assert_eq!(problems[3], &Problem::UnknownComponent("sparkle".to_string()));
+        assert_eq!(errors[4].path, "builder[3].wallet");
+    }
+
+    #[test]
+    fn test_registered_components_are_built() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Ripeness(u8);
+
+        let mut components = components::ComponentRegistry::default();
+        components.register("ripeness", |ripeness: u8, _| Some(Ripeness(ripeness)));
+        let factory = EntityFactory::parse_with(
+            r##"{ "builder": [
+                { "id": "pear", "name": "Pear", "renderable": { "glyph": { "ch": "p", "render_order": 0 } }, "ripeness": 4 }
+            ] }"##,
+            components,
+        )
+        .unwrap();
+        let universe = Universe::new();
+        let mut world = universe.create_world();
+        let mut buffer = CommandBuffer::new(&world);
+        let pear = factory.build("pear", None, &mut buffer).unwrap();
+        buffer.write(&mut world);
+        assert_eq!(*world.get_component::<Ripeness>(pear).unwrap(), Ripeness(4));
+    }
+}
@@ -0,0 +1,112 @@
+use super::{Customer, Negotiator, Priority, Stack, Value, Viewshed, Wallet};
+use crate::component;
+use crate::geom::Point;
+use legion::prelude::*;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Adds one parsed component to a freshly built entity, which may be given a position.
+pub type Attach = Box<dyn Fn(Entity, Option<Point>, &mut CommandBuffer) + Send + Sync>;
+type Loader = Box<dyn Fn(&serde_json::Value) -> Result<Attach, serde_json::Error> + Send + Sync>;
+
+/// Maps keys in entity data to the components they build, so new components don't need a field
+/// on `EntityBuilder`.
+pub struct ComponentRegistry {
+    loaders: HashMap<String, Loader>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self {
+            loaders: HashMap::new(),
+        }
+    }
+
+    /// Parses the data under `key` as `D` and turns it into a component with `make`, which can
+    /// return `None` to leave the component off, e.g. for a `false` flag.
+    pub fn register<D, C, F>(&mut self, key: &str, make: F)
+    where
+        D: DeserializeOwned + Clone + Send + Sync + 'static,
+        C: Component,
+        F: Fn(D, Option<Point>) -> Option<C> + Send + Sync + 'static,
+    {
+        let make = Arc::new(make);
+        let loader = move |value: &serde_json::Value| -> Result<Attach, serde_json::Error> {
+            let data: D = serde_json::from_value(value.clone())?;
+            let make = make.clone();
+            Ok(Box::new(move |entity, position, buffer: &mut CommandBuffer| {
+                if let Some(component) = make(data.clone(), position) {
+                    buffer.add_component(entity, component);
+                }
+            }))
+        };
+        self.loaders.insert(key.to_string(), Box::new(loader));
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.loaders.contains_key(key)
+    }
+
+    /// `None` if nothing is registered under `key`.
+    pub fn load(&self, key: &str, value: &serde_json::Value) -> Option<Result<Attach, serde_json::Error>> {
+        self.loaders.get(key).map(|loader| loader(value))
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("priority", |priority: Priority, _| {
+            Some(component::Priority {
+                value: priority.value,
+            })
+        });
+        registry.register("tradeable", |tradeable: bool, _| {
+            if tradeable {
+                Some(component::Tradeable { request: None })
+            } else {
+                None
+            }
+        });
+        registry.register("customer", |customer: Customer, position: Option<Point>| {
+            Some(component::Customer {
+                state: component::CustomerState::Entering,
+                target: None,
+                exit: position.unwrap_or_else(Point::zero),
+                patience: customer.patience,
+                stuck: 0,
+            })
+        });
+        registry.register("negotiator", |negotiator: Negotiator, _| {
+            Some(component::Negotiator {
+                policy: negotiator.policy,
+                patience: negotiator.patience,
+            })
+        });
+        registry.register("value", |value: Value, _| {
+            Some(component::Value {
+                base: value.base,
+                rarity: value.rarity.unwrap_or(component::Rarity::Common),
+            })
+        });
+        registry.register("wallet", |wallet: Wallet, _| {
+            Some(component::Wallet { gold: wallet.gold })
+        });
+        registry.register("viewshed", |viewshed: Viewshed, _| {
+            Some(component::Viewshed {
+                range: viewshed.range,
+                visible_tiles: vec![],
+                origin: None,
+            })
+        });
+        // Every item is built as a single unit; stacks grow as inventories merge them.
+        registry.register("stack", |stack: Stack, _| {
+            Some(component::Quantity {
+                count: 1,
+                max: stack.max.max(1),
+            })
+        });
+        registry
+    }
+}
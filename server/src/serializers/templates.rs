@@ -0,0 +1,102 @@
+use super::{Problem, SchemaError};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// An entry of the entity file with everything it `extends` merged underneath it.
+pub struct Resolved {
+    pub index: usize,
+    pub is_abstract: bool,
+    pub value: Value,
+}
+
+/// Resolves every entry that has a unique id and a valid `extends` chain. Problems are pushed
+/// onto `errors[index]` for the entry they belong to.
+pub fn resolve(entries: &[Value], errors: &mut Vec<Vec<SchemaError>>) -> Vec<Resolved> {
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let path = format!("builder[{}]", index);
+        match entry.get("id").and_then(Value::as_str) {
+            Some(id) if ids.contains_key(id) => errors[index].push(SchemaError {
+                path: format!("{}.id", path),
+                problem: Problem::DuplicateId(id.to_string()),
+            }),
+            Some(id) => {
+                ids.insert(id, index);
+            }
+            None => errors[index].push(SchemaError {
+                path,
+                problem: Problem::Malformed("expected an object with a string id".to_string()),
+            }),
+        }
+    }
+
+    let mut resolved = vec![];
+    for (index, entry) in entries.iter().enumerate() {
+        let id = entry.get("id").and_then(Value::as_str).unwrap_or_default();
+        if ids.get(id) != Some(&index) {
+            continue;
+        }
+        let path = format!("builder[{}].extends", index);
+        let mut chain = vec![index];
+        let mut current = index;
+        let mut broken = false;
+        while let Some(parent) = entries[current].get("extends") {
+            let parent_id = parent.as_str().unwrap_or_default();
+            match ids.get(parent_id) {
+                Some(parent) if chain.contains(parent) => {
+                    errors[index].push(SchemaError {
+                        path: path.clone(),
+                        problem: Problem::CyclicTemplate(id.to_string()),
+                    });
+                    broken = true;
+                    break;
+                }
+                Some(parent) => {
+                    chain.push(*parent);
+                    current = *parent;
+                }
+                None => {
+                    // A broken ancestor reports itself, so only flag our own `extends`.
+                    if current == index {
+                        errors[index].push(SchemaError {
+                            path: path.clone(),
+                            problem: Problem::UnknownId(parent_id.to_string()),
+                        });
+                    }
+                    broken = true;
+                    break;
+                }
+            }
+        }
+        if broken {
+            continue;
+        }
+
+        let mut value = Value::Object(Map::new());
+        for link in chain.iter().rev() {
+            merge(&mut value, &entries[*link]);
+        }
+        let object = value.as_object_mut().unwrap();
+        object.remove("extends");
+        // Being a template isn't inherited.
+        object.remove("abstract");
+        resolved.push(Resolved {
+            index,
+            is_abstract: entry.get("abstract").and_then(Value::as_bool).unwrap_or(false),
+            value,
+        });
+    }
+    resolved
+}
+
+/// Objects merge key by key; anything else in `overlay` replaces what's in `base`.
+fn merge(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay.iter() {
+                merge(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
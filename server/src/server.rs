@@ -0,0 +1,1345 @@
+use crate::component;
+use crate::component::{TurnState, Tradeable, DisplayCabinet};
+use crate::message::{Action, Message};
+
+use crate::map::Map;
+use crate::geom::Point;
+use crate::gamestate::RunState;
+use crate::map_builders::BuiltMap;
+use crate::systems::trade_system::trade_system;
+use crate::systems::index_system::index_system;
+use crate::systems::turn_system::{turn_system, PendingMoves};
+
+use crate::{map_builders::factories::shop_builder, serializers::entity_factory};
+use crate::serializers::FactoryError;
+use crate::map_builders::prefab::PrefabLibrary;
+use crate::map_builders::area_spawns::AreaSpawns;
+use crate::map_builders::factories::{cellar_builder, drunk_builder, preview_builder, random_builder};
+use crate::map_builders::spawn_table::{SpawnKey, SpawnTables};
+use crate::map_builders::MetaMapBuilder;
+use crate::stacks;
+use std::collections::HashMap;
+use instant::Instant;
+use legion::prelude::*;
+use crate::rng::GameRng;
+use rand::SeedableRng;
+use crate::resources::trade_handler::{TradeHandler, TradeState};
+use crate::resources::message_queue::MessageQueue;
+use crate::resources::action_queue::ActionQueue;
+use crate::systems::transaction_system::settle_transactions;
+use crate::systems::customer_system::customer_system;
+use crate::systems::negotiation_system::negotiation_system;
+use crate::systems::visibility_system::visibility_system;
+use crate::systems::movement_system::movement_system;
+use crate::map::pathfinding::{a_star, Occupancy};
+use crate::resources::negotiation::NegotiationPolicies;
+use rand::Rng;
+use crate::save::{self, SaveError};
+use crate::resources::network_registry::{NetworkIdError, NetworkRegistry};
+use crate::resources::market::MarketDemand;
+use crate::resources::shop_day::ShopDay;
+use crate::resources::customer_schedule::CustomerSchedule;
+use crate::resources::movement_rules::{MovementRules, Topology};
+use crate::resources::levels::{LevelLink, Levels, CELLAR_DEPTH, SHOP_DEPTH, STREET_DEPTH};
+use crate::map::TileType;
+use std::path::Path;
+
+pub struct Server {
+    pub world: World,
+    pub resources: Resources,
+    pub universe: Universe,
+    schedule: Schedule,
+    run_state: RunState,
+    paused_from: Option<RunState>,
+    map_state: MapState,
+    factory: entity_factory::EntityFactory,
+    prefabs: PrefabLibrary,
+    spawn_tables: SpawnTables,
+}
+pub struct MapState {
+    mapgen_index: usize,
+    mapgen_built_map: BuiltMap,
+    mapgen_timer: Instant,
+    /// The shop and its map, set aside while a preview plays back in their place.
+    previewing_from: Option<(BuiltMap, Map)>,
+}
+
+const CUSTOMER_ARRIVAL_ROUNDS: (u64, u64) = (4, 12);
+const CUSTOMER_IDS: [&str; 3] = ["haggler", "impulsive", "cheapskate"];
+/// Ids built by name rather than from prefabs or spawn tables, so every entity file needs them.
+pub const REQUIRED_IDS: [&str; 5] = ["player", "display", "haggler", "impulsive", "cheapskate"];
+const OPENING_ROUNDS: u64 = 60;
+const MAPGEN_FRAME_MILLIS: u128 = 100;
+const PREVIEW_SIZE: (i32, i32) = (80, 50);
+const CELLAR_SIZE: (i32, i32) = (40, 30);
+const STREET_SIZE: (i32, i32) = (80, 50);
+
+impl Server {
+    fn setup_ecs() -> (Universe, World, Resources) {
+        let universe = Universe::new();
+        let world = universe.create_world();
+
+        let mut resources = Resources::default();
+        let turn = PendingMoves::new();
+        let message_queue = MessageQueue::new();
+        let action_queue = ActionQueue::new();
+        let trade_handler = TradeHandler::new();
+        resources.insert(turn);
+        resources.insert(message_queue);
+        resources.insert(action_queue);
+        resources.insert(trade_handler);
+        resources.insert(NegotiationPolicies::new());
+        resources.insert(NetworkRegistry::new());
+        resources.insert(MarketDemand::new());
+        resources.insert(ShopDay::new());
+        resources.insert(MovementRules::default());
+        resources.insert(Levels::new());
+        resources.insert(CustomerSchedule::new(CUSTOMER_ARRIVAL_ROUNDS.0));
+
+        (universe, world, resources)
+    }
+
+    pub fn new(seed: u64) -> Self {
+        Self::from_factory(
+            entity_factory::EntityFactory::load(),
+            PrefabLibrary::load(),
+            SpawnTables::load(),
+            seed,
+        )
+        .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Fails if the factory can't build everything the server, prefabs or spawn tables need.
+    pub fn from_factory(
+        factory: entity_factory::EntityFactory,
+        prefabs: PrefabLibrary,
+        spawn_tables: SpawnTables,
+        seed: u64,
+    ) -> Result<Self, FactoryError> {
+        Self::check_ids(&factory, &prefabs, &spawn_tables)?;
+        let (universe, world, mut resources) = Self::setup_ecs();
+        let mut rng = GameRng::seed_from_u64(seed);
+        let prefab = prefabs.random(&mut rng);
+        let stock = spawn_tables.get(&SpawnKey::Shop(prefab.id.clone()));
+        let built_map = shop_builder(prefab, stock, &mut rng);
+        resources.get_mut::<ShopDay>().unwrap().shop = prefab.id.clone();
+        let BuiltMap {
+            spawn_list: _,
+            map,
+            starting_position: _,
+            rooms: _,
+            history,
+            with_history,
+        } = &built_map;
+        {
+            let mut levels = resources.get_mut::<Levels>().unwrap();
+            for (index, tile) in map.tiles.iter().enumerate() {
+                let target = match tile {
+                    TileType::DownStairs => CELLAR_DEPTH,
+                    TileType::Door => STREET_DEPTH,
+                    _ => continue,
+                };
+                levels.links.push(LevelLink {
+                    depth: SHOP_DEPTH,
+                    position: map.index_to_point(index),
+                    target,
+                });
+            }
+        }
+        let run_state = if *with_history && !history.is_empty() {
+            resources.insert(history[0].clone());
+            RunState::MapGeneration
+        } else {
+            resources.insert(map.clone());
+            RunState::Initializing
+        };
+        resources.insert(rng);
+
+        let schedule = Schedule::builder()
+            .add_system(index_system())
+            .add_system(turn_system())
+            .add_system(negotiation_system())
+            .add_system(trade_system())
+            .add_system(customer_system())
+            .add_system(movement_system())
+            .add_system(visibility_system())
+            .build();
+
+        Ok(Server {
+            world,
+            resources,
+            schedule,
+            universe,
+            run_state,
+            paused_from: None,
+            map_state: MapState {
+                mapgen_index: 0,
+                mapgen_built_map: built_map,
+                mapgen_timer: Instant::now(),
+                previewing_from: None,
+            },
+            factory,
+            prefabs,
+            spawn_tables,
+        })
+    }
+
+    pub fn check_ids(
+        factory: &entity_factory::EntityFactory,
+        prefabs: &PrefabLibrary,
+        spawn_tables: &SpawnTables,
+    ) -> Result<(), FactoryError> {
+        factory.check_defines(&REQUIRED_IDS)?;
+        prefabs.check_ids(factory)?;
+        spawn_tables.check_ids(factory)
+    }
+
+    fn insert_entities(&mut self) {
+        let mut command_buffer = CommandBuffer::new(&self.world);
+        let position = self
+            .map_state
+            .mapgen_built_map
+            .starting_position
+            .unwrap()
+            .clone();
+        let player = self
+            .factory
+            .build("player", Some(position), &mut command_buffer)
+            .expect("The player is a required id");
+        command_buffer.add_tag(player, component::Player);
+        command_buffer.write(&mut self.world);
+        let spawns = Self::spawn_points(&self.map_state.mapgen_built_map);
+        self.spawn_entries(&spawns);
+    }
+
+    fn spawn_points(built_map: &BuiltMap) -> Vec<(Point, String)> {
+        built_map
+            .spawn_list
+            .iter()
+            .map(|(index, id)| (built_map.map.index_to_point(*index), id.clone()))
+            .collect()
+    }
+
+    /// Builds each entry at its position. An entry sharing a position with an earlier one that
+    /// has an inventory is put inside it instead, or dropped if it doesn't fit.
+    fn spawn_entries(&mut self, spawns: &[(Point, String)]) {
+        let mut command_buffer = CommandBuffer::new(&self.world);
+        let mut containers: HashMap<Point, Entity> = HashMap::new();
+        let mut stock = vec![];
+        for (position, id) in spawns.iter() {
+            if let Some(container) = containers.get(position) {
+                if let Some(item) = self.factory.build(id, None, &mut command_buffer) {
+                    stock.push((*container, item));
+                }
+                continue;
+            }
+            let entity = match self.factory.build(id, Some(*position), &mut command_buffer) {
+                Some(entity) => entity,
+                None => continue,
+            };
+            if self.factory.has_inventory(id) {
+                containers.insert(*position, entity);
+            }
+        }
+        command_buffer.write(&mut self.world);
+        for (container, item) in stock {
+            if !stacks::put(&mut self.world, container, item) {
+                self.world.delete(item);
+            }
+        }
+    }
+
+    /// Rolls `rolls` items from `key`'s table into the container, dropping any that don't fit.
+    fn stock_from_table(&mut self, container: Entity, key: &SpawnKey) {
+        let table = match self.spawn_tables.get(key) {
+            Some(table) => table,
+            None => return,
+        };
+        if self.world.get_component::<component::Inventory>(container).is_none() {
+            return;
+        }
+        let ids: Vec<String> = {
+            let mut rng = self.resources.get_mut::<GameRng>().unwrap();
+            (0..table.rolls).filter_map(|_| table.roll(&mut rng)).collect()
+        };
+        let mut command_buffer = CommandBuffer::new(&self.world);
+        let items: Vec<Entity> = ids
+            .iter()
+            .filter_map(|id| self.factory.build(id, None, &mut command_buffer))
+            .collect();
+        command_buffer.write(&mut self.world);
+        for item in items {
+            if !stacks::put(&mut self.world, container, item) {
+                self.world.delete(item);
+            }
+        }
+    }
+
+    /// Fills every display with room from the shop's stock table, so each day starts with fresh
+    /// stock alongside whatever didn't sell.
+    fn restock_displays(&mut self) {
+        let query = <(Read<component::Inventory>)>::query().filter(tag::<component::DisplayCabinet>());
+        let displays: Vec<Entity> = query.iter_entities(&self.world).map(|(entity, _)| entity).collect();
+        let key = SpawnKey::Shop(self.resources.get::<ShopDay>().unwrap().shop.clone());
+        for display in displays {
+            self.stock_from_table(display, &key);
+        }
+    }
+
+    fn current_round(&self) -> u64 {
+        self.resources.get::<PendingMoves>().unwrap().round()
+    }
+
+    /// Customers on the current level. Anyone parked on another one waits there until the
+    /// player comes back, so they neither fill the shop nor hold up closing.
+    fn customer_count(&self) -> usize {
+        let query = <(Read<component::Customer>)>::query().filter(!component::<component::Parked>());
+        query.iter(&self.world).count()
+    }
+
+    fn spawn_customers(&mut self) {
+        let round = self.current_round();
+        let schedule = *self.resources.get::<CustomerSchedule>().unwrap();
+        if round < schedule.next_arrival {
+            return;
+        }
+        if self.customer_count() >= schedule.max_customers {
+            return;
+        }
+        let door = {
+            let map = self.resources.get::<Map>().unwrap();
+            if map.depth != SHOP_DEPTH {
+                return;
+            }
+            map.doors()
+                .into_iter()
+                .find(|door| map.tile_content[map.point_to_index(*door)].is_none())
+        };
+        if let Some(door) = door {
+            let mut rng = self.resources.get_mut::<GameRng>().unwrap();
+            let id = CUSTOMER_IDS[rng.gen_range(0, CUSTOMER_IDS.len())];
+            self.resources.get_mut::<CustomerSchedule>().unwrap().next_arrival =
+                round + rng.gen_range(CUSTOMER_ARRIVAL_ROUNDS.0, CUSTOMER_ARRIVAL_ROUNDS.1);
+            std::mem::drop(rng);
+            let mut command_buffer = CommandBuffer::new(&self.world);
+            if let Some(customer) = self.factory.build(id, Some(door), &mut command_buffer) {
+                command_buffer.write(&mut self.world);
+                self.stock_from_table(customer, &SpawnKey::Inventory(id.to_string()));
+            }
+        }
+    }
+
+    fn check_level_transition(&mut self) {
+        let player = self.get_player();
+        let position: Point = match self.world.get_component::<component::Position>(player) {
+            Some(position) => (*position).into(),
+            None => return,
+        };
+        let depth = self.resources.get::<Map>().unwrap().depth;
+        let link = {
+            let mut levels = self.resources.get_mut::<Levels>().unwrap();
+            if levels.arrived_at == Some(position) {
+                return;
+            }
+            levels.arrived_at = None;
+            levels.link_at(depth, position)
+        };
+        if let Some(link) = link {
+            self.change_level(player, link.depth, link.target);
+        }
+    }
+
+    fn generate_level(&mut self, depth: i32, from: i32) -> BuiltMap {
+        let mut built_map = {
+            let mut rng = self.resources.get_mut::<GameRng>().unwrap();
+            let stock = self.spawn_tables.get(&SpawnKey::Depth(depth));
+            match (depth, stock) {
+                (CELLAR_DEPTH, Some(stock)) => cellar_builder(CELLAR_SIZE.into(), depth, stock, &mut rng),
+                (CELLAR_DEPTH, None) => drunk_builder(CELLAR_SIZE.into(), depth, &mut rng),
+                (_, stock) => {
+                    let mut built_map = random_builder(STREET_SIZE.into(), depth, &mut rng);
+                    if let Some(stock) = stock {
+                        AreaSpawns::new(8, 2, stock.clone()).mutate(&mut rng, &mut built_map);
+                    }
+                    built_map
+                }
+            }
+        };
+        let start = built_map.starting_position.unwrap();
+        let tile = if depth > from {
+            TileType::UpStairs
+        } else {
+            TileType::Door
+        };
+        built_map.map.set_type(start, tile);
+        built_map.map.refresh_blocked();
+        self.resources.get_mut::<Levels>().unwrap().links.push(LevelLink {
+            depth,
+            position: start,
+            target: from,
+        });
+        built_map
+    }
+
+    /// Parks everything on level `from`, swaps in level `to` (building it on the first visit)
+    /// and brings back whatever was parked there.
+    fn change_level(&mut self, player: Entity, from: i32, to: i32) {
+        {
+            let mut levels = self.resources.get_mut::<Levels>().unwrap();
+            // A level that's already built needs a way back to where the player is coming from.
+            if levels.is_generated(to) && levels.arrival(to, from).is_none() {
+                levels.arrived_at = self
+                    .world
+                    .get_component::<component::Position>(player)
+                    .map(|position| (*position).into());
+                std::mem::drop(levels);
+                self.reject("That way doesn't lead anywhere from here.");
+                return;
+            }
+        }
+        let mut command_buffer = CommandBuffer::new(&self.world);
+        let query = <(Read<component::Position>)>::query();
+        for (entity, position) in query.iter_entities(&self.world) {
+            if entity == player {
+                continue;
+            }
+            command_buffer.remove_component::<component::Position>(entity);
+            command_buffer.add_component(
+                entity,
+                component::Parked {
+                    depth: from,
+                    position: (*position).into(),
+                },
+            );
+        }
+        let query = <(Read<component::Parked>)>::query();
+        for (entity, parked) in query.iter_entities(&self.world) {
+            if parked.depth != to {
+                continue;
+            }
+            command_buffer.remove_component::<component::Parked>(entity);
+            command_buffer.add_component(
+                entity,
+                component::Position {
+                    x: parked.position.x,
+                    y: parked.position.y,
+                },
+            );
+        }
+        command_buffer.remove_component::<component::MovePlan>(player);
+        command_buffer.write(&mut self.world);
+
+        let generated = self.resources.get::<Levels>().unwrap().is_generated(to);
+        let built_map = if generated {
+            None
+        } else {
+            Some(self.generate_level(to, from))
+        };
+        let target_map = match &built_map {
+            Some(built_map) => built_map.map.clone(),
+            None => self.resources.get_mut::<Levels>().unwrap().parked_maps.remove(&to).unwrap(),
+        };
+        let previous = std::mem::replace(&mut *self.resources.get_mut::<Map>().unwrap(), target_map);
+        let mut levels = self.resources.get_mut::<Levels>().unwrap();
+        levels.parked_maps.insert(from, previous);
+        let arrival = levels.arrival(to, from).expect("New levels are linked back when generated");
+        levels.arrived_at = Some(arrival);
+        std::mem::drop(levels);
+
+        if let Some(built_map) = &built_map {
+            self.spawn_entries(&Self::spawn_points(built_map));
+        }
+        self.world
+            .get_component_mut::<component::Position>(player)
+            .map(|mut position| {
+                position.x = arrival.x;
+                position.y = arrival.y;
+            });
+        self.world
+            .get_component_mut::<component::Viewshed>(player)
+            .map(|mut viewshed| viewshed.origin = None);
+        self.push_message(Message::MapUpdated);
+    }
+
+    fn push_message(&mut self, message: Message) {
+        let mut message_queue = self.resources.get_mut::<MessageQueue>().unwrap();
+        message_queue.push(message);
+    }
+
+    fn set_run_state(&mut self, phase: RunState) {
+        self.run_state = phase;
+        let day = self.resources.get::<ShopDay>().unwrap().day;
+        self.push_message(Message::PhaseChanged { day, phase });
+    }
+
+    fn step_map_generation(&mut self) {
+        if self.map_state.mapgen_timer.elapsed().as_millis() < MAPGEN_FRAME_MILLIS {
+            return;
+        }
+        self.map_state.mapgen_timer = Instant::now();
+        self.map_state.mapgen_index += 1;
+        let built_map = &self.map_state.mapgen_built_map;
+        let finished = self.map_state.mapgen_index >= built_map.history.len();
+        let frame = match built_map.history.get(self.map_state.mapgen_index) {
+            Some(frame) => frame.clone(),
+            None => built_map.map.clone(),
+        };
+        self.resources.insert(frame);
+        if finished {
+            if let Some((built_map, map)) = self.map_state.previewing_from.take() {
+                self.map_state.mapgen_built_map = built_map;
+                self.resources.insert(map);
+            }
+            self.set_run_state(RunState::Initializing);
+        }
+        self.push_message(Message::MapUpdated);
+    }
+
+    /// Plays back how one of the named generators builds a map, then goes back to the shop.
+    /// Only possible before the world has been populated.
+    pub fn preview_generator(&mut self, name: &str) -> bool {
+        if self.run_state != RunState::Initializing {
+            self.reject("Map generation can only be previewed before the game starts.");
+            return false;
+        }
+        let built_map = {
+            let mut rng = self.resources.get_mut::<GameRng>().unwrap();
+            preview_builder(name, PREVIEW_SIZE.into(), 1, &mut rng)
+        };
+        match built_map {
+            Some(built_map) if !built_map.history.is_empty() => {
+                let first_frame = built_map.history[0].clone();
+                let map = std::mem::replace(&mut *self.resources.get_mut::<Map>().unwrap(), first_frame);
+                let shop = std::mem::replace(&mut self.map_state.mapgen_built_map, built_map);
+                self.map_state.mapgen_index = 0;
+                self.map_state.mapgen_timer = Instant::now();
+                self.map_state.previewing_from = Some((shop, map));
+                self.push_message(Message::MapUpdated);
+                self.set_run_state(RunState::MapGeneration);
+                true
+            }
+            _ => {
+                self.reject("Unknown map generator.");
+                false
+            }
+        }
+    }
+
+    pub fn run_state(&self) -> RunState {
+        self.run_state
+    }
+
+    pub fn open_shop(&mut self) -> bool {
+        if self.run_state != RunState::Restock {
+            self.reject("The shop can only be opened in the morning.");
+            return false;
+        }
+        let round = self.current_round();
+        self.resources.get_mut::<ShopDay>().unwrap().closes_at_round = round + OPENING_ROUNDS;
+        self.resources.get_mut::<CustomerSchedule>().unwrap().next_arrival = round + CUSTOMER_ARRIVAL_ROUNDS.0;
+        self.set_run_state(RunState::Running);
+        true
+    }
+
+    fn start_closing(&mut self) {
+        let query = <(Write<component::Customer>)>::query();
+        for mut customer in query.iter_mut(&mut self.world) {
+            if customer.state != component::CustomerState::Trading {
+                customer.state = component::CustomerState::Leaving;
+            }
+        }
+        self.set_run_state(RunState::Closing);
+    }
+
+    pub fn pause(&mut self) {
+        if self.run_state != RunState::Paused {
+            self.paused_from = Some(self.run_state);
+            self.set_run_state(RunState::Paused);
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(phase) = self.paused_from.take() {
+            self.set_run_state(phase);
+        }
+    }
+
+    pub fn tick(&mut self) -> Vec<Message> {
+        match self.run_state {
+            RunState::Restock | RunState::Running | RunState::Closing => {
+                let world = &mut self.world;
+                let resources = &mut self.resources;
+                let schedule = &mut self.schedule;
+                settle_transactions(world, resources, &self.factory);
+                schedule.execute(world, resources);
+                self.check_level_transition();
+                match self.run_state {
+                    RunState::Running => {
+                        self.spawn_customers();
+                        let closes_at = self.resources.get::<ShopDay>().unwrap().closes_at_round;
+                        if self.current_round() >= closes_at {
+                            self.start_closing();
+                        }
+                    }
+                    RunState::Closing => {
+                        if self.customer_count() == 0 {
+                            self.set_run_state(RunState::EndOfDay);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            RunState::EndOfDay => {
+                let ledger = self.resources.get_mut::<ShopDay>().unwrap().next_day();
+                self.resources.get_mut::<MarketDemand>().unwrap().next_day();
+                self.push_message(Message::DaySummary(ledger));
+                self.restock_displays();
+                self.set_run_state(RunState::Restock);
+            }
+            RunState::Initializing => {
+                let resources = &mut self.resources;
+                let mut map = resources.get_mut::<Map>().unwrap();
+                map.refresh_blocked();
+                std::mem::drop(map);
+                self.insert_entities();
+                self.set_run_state(RunState::Restock);
+            }
+            RunState::MapGeneration => self.step_map_generation(),
+            RunState::Paused => {}
+        };
+        self.sync_network_ids();
+        let mut action_queue = self.resources.get_mut::<ActionQueue>().unwrap();
+        action_queue.step();
+        let mut message_queue = self.resources.get_mut::<MessageQueue>().unwrap();
+        let messages = message_queue.get_messages();
+        message_queue.clear();
+        messages
+    }
+
+    fn sync_network_ids(&mut self) {
+        let mut registry = self.resources.get_mut::<NetworkRegistry>().unwrap();
+        let query = <(Read<component::Name>)>::query().filter(!component::<component::NetworkId>());
+        let unregistered: Vec<Entity> = query.iter_entities(&self.world).map(|(entity, _)| entity).collect();
+        for entity in unregistered {
+            let id = registry.register(entity);
+            let _ = self.world.add_component(entity, id);
+        }
+        let world = &self.world;
+        registry.retain(|entity| world.is_alive(entity));
+    }
+
+    pub fn network_id(&self, entity: Entity) -> Option<component::NetworkId> {
+        self.resources.get::<NetworkRegistry>().unwrap().id_of(entity)
+    }
+
+    pub fn resolve(&self, id: component::NetworkId) -> Result<Entity, NetworkIdError> {
+        let entity = self.resources.get::<NetworkRegistry>().unwrap().resolve(id)?;
+        if self.world.is_alive(entity) {
+            Ok(entity)
+        } else {
+            Err(NetworkIdError::Stale(id.0))
+        }
+    }
+
+    /// Re-reads the entity definitions, refusing them if they drop an id the game still builds.
+    /// With `reapply`, live entities built from a definition pick up its new `Renderable` and
+    /// `Name`. Returns how many entities changed.
+    pub fn reload_definitions(&mut self, reapply: bool) -> Result<usize, FactoryError> {
+        let (prefabs, spawn_tables) = (&self.prefabs, &self.spawn_tables);
+        self.factory
+            .reload(|factory| Self::check_ids(factory, prefabs, spawn_tables))?;
+        if !reapply {
+            return Ok(0);
+        }
+        let query = <(Read<component::Prototype>)>::query();
+        let prototypes: Vec<(Entity, String)> = query
+            .iter_entities(&self.world)
+            .map(|(entity, prototype)| (entity, prototype.id.clone()))
+            .collect();
+        let mut changed = vec![];
+        for (entity, id) in prototypes {
+            let (renderable, name) = match (self.factory.renderable(&id), self.factory.name(&id)) {
+                (Some(renderable), Some(name)) => (renderable, name),
+                // The definition was removed, so leave the entity as it is.
+                _ => continue,
+            };
+            let mut dirty = false;
+            if let Some(mut current) = self.world.get_component_mut::<component::Renderable>(entity) {
+                if *current != renderable {
+                    *current = renderable;
+                    dirty = true;
+                }
+            }
+            if let Some(mut current) = self.world.get_component_mut::<component::Name>(entity) {
+                if *current != name {
+                    *current = name;
+                    dirty = true;
+                }
+            }
+            if dirty {
+                changed.push(entity);
+            }
+        }
+        let count = changed.len();
+        if count > 0 {
+            self.push_message(Message::EntitiesChanged(changed));
+        }
+        Ok(count)
+    }
+
+    /// Reloads the definitions if their file has changed since they were read.
+    pub fn poll_definitions(&mut self, reapply: bool) -> Option<Result<usize, FactoryError>> {
+        if !self.factory.is_stale() {
+            return None;
+        }
+        Some(self.reload_definitions(reapply))
+    }
+
+    pub fn suggested_price(&self, item: Entity) -> Option<u32> {
+        let value = self.world.get_component::<component::Value>(item)?;
+        let kind = self
+            .world
+            .get_component::<component::Prototype>(item)
+            .map_or(String::new(), |prototype| prototype.id.clone());
+        let market = self.resources.get::<MarketDemand>().unwrap();
+        Some(market.price(&kind, &value))
+    }
+
+    pub fn get_player(&self) -> Entity {
+        let query = <(Read<component::Position>)>::query().filter(tag::<component::Player>());
+        query.iter_entities(&self.world).next().unwrap().0
+    }
+
+    pub fn get_player_inventory(&self) -> Vec<Entity> {
+        let query = <(Read<component::Inventory>)>::query().filter(tag::<component::Player>());
+        query
+            .iter(&self.world)
+            .next()
+            .unwrap()
+            .as_ref()
+            .contents
+            .clone()
+    }
+
+    pub fn handle_action(&mut self, entity: Entity, action: Action) {}
+
+    fn reject(&mut self, reason: &str) {
+        let mut message_queue = self.resources.get_mut::<MessageQueue>().unwrap();
+        message_queue.push(Message::ActionRejected(reason.to_string()));
+    }
+
+    pub fn try_player_put(&mut self, entity: Entity, player_inv: Entity, count: u32) -> bool {
+        let player_entity = self.get_player();
+        self.try_move_units(player_entity, entity, player_inv, count, "There's no room left in there.")
+    }
+
+    pub fn try_player_take(&mut self, entity: Entity, item: Entity, count: u32) -> bool {
+        let player_entity = self.get_player();
+        self.try_move_units(entity, player_entity, item, count, "Your hands are full.")
+    }
+
+    /// Moves `count` units of `item` between inventories, splitting and merging stacks as needed.
+    fn try_move_units(&mut self, from: Entity, to: Entity, item: Entity, count: u32, full: &str) -> bool {
+        if self.world.get_component::<component::Inventory>(to).is_none() {
+            return false;
+        }
+        let units = match stacks::take(&mut self.world, &self.factory, from, item, count) {
+            Some(units) => units,
+            None => return false,
+        };
+        if stacks::put(&mut self.world, to, units) {
+            return true;
+        }
+        stacks::put_back(&mut self.world, from, units);
+        self.reject(full);
+        false
+    }
+
+    pub fn try_move_player(&mut self, delta_x: i32, delta_y: i32) -> bool {
+        if !self.run_state.is_simulating() {
+            return false;
+        }
+        let world = &mut self.world;
+        let resources = &mut self.resources;
+        let map = resources.get_mut::<Map>().unwrap();
+        let rules = resources.get::<MovementRules>().unwrap();
+        let query = <(Write<component::Position>, Write<component::ActiveTurn>)>::query()
+            .filter(tag::<component::Player>());
+
+        let mut command_buffer = CommandBuffer::new(&world);
+
+        let mut moved = false;
+        for (entity, (mut pos, mut turn)) in query.iter_entities_mut(world) {
+            let from = Point::new(pos.x, pos.y);
+            let desired = Point::new(pos.x + delta_x, pos.y + delta_y);
+            if rules.can_step(&map, from, desired)
+                && map.tile_content[map.point_to_index(desired)] == None
+            {
+                pos.x = desired.x;
+                pos.y = desired.y;
+                moved = true;
+            }
+            turn.state = TurnState::DONE;
+            command_buffer.remove_component::<component::MovePlan>(entity);
+        }
+
+        command_buffer.write(world);
+        moved
+    }
+
+    pub fn set_movement_topology(&mut self, topology: Topology) {
+        self.resources.insert(MovementRules::new(topology));
+    }
+
+    pub fn try_move_to(&mut self, target: Point) -> bool {
+        if !self.run_state.is_simulating() {
+            return false;
+        }
+        let player = self.get_player();
+        let position: Point = match self.world.get_component::<component::Position>(player) {
+            Some(position) => (*position).into(),
+            None => return false,
+        };
+        let path = {
+            let map = self.resources.get::<Map>().unwrap();
+            let rules = self.resources.get::<MovementRules>().unwrap();
+            a_star(&map, &rules, position, target, Occupancy::Avoid)
+        };
+        match path {
+            Some(steps) if !steps.is_empty() => {
+                let _ = self.world.add_component(player, component::MovePlan { steps });
+                true
+            }
+            _ => {
+                self.reject("There's no way to get there.");
+                false
+            }
+        }
+    }
+
+    /// Offers `count` units of `target` from the player's inventory to `buyer`.
+    pub fn try_start_trade(&mut self, buyer: Entity, target: Entity, count: u32) -> bool {
+        let seller = self.get_player();
+        let idle = |entity| {
+            self.world
+                .get_component::<Tradeable>(entity)
+                .map_or(false, |tradeable| tradeable.request.is_none())
+        };
+        let (buyer_idle, seller_idle) = (idle(buyer), idle(seller));
+        if buyer == seller || !buyer_idle {
+            self.reject("They aren't looking to trade.");
+            return false;
+        }
+        if !seller_idle {
+            self.reject("You're already trading.");
+            return false;
+        }
+        let available = stacks::quantity(&self.world, target);
+        if !self.get_player_inventory().contains(&target) || count == 0 || count > available {
+            self.reject("You don't have that many to sell.");
+            return false;
+        }
+        let mut trade_handler = self.resources.get_mut::<TradeHandler>().unwrap();
+        let mut message_queue= self.resources.get_mut::<MessageQueue>().unwrap();
+        let trade_request = trade_handler.start(
+            target,
+            count,
+            buyer,
+            seller,
+            buyer
+        );
+        self.world.get_component_mut::<Tradeable>(buyer).map(|mut tradeable| tradeable.request = Some(trade_request));
+        self.world.get_component_mut::<Tradeable>(seller).map(|mut tradeable| tradeable.request = Some(trade_request));
+        trade_handler.get_trade(trade_request).map(|trade| message_queue.push(Message::TradeEvent(trade)));
+        true
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let run_state = self.paused_from.unwrap_or(self.run_state);
+        let file = save::capture(&self.world, &self.resources, run_state);
+        save::write(path, &file)
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SaveError> {
+        let file = save::read(path)?;
+        let mut world = self.universe.create_world();
+        self.run_state = save::restore(file, &mut world, &mut self.resources)?;
+        self.paused_from = None;
+        self.world = world;
+        self.resources.get_mut::<NetworkRegistry>().unwrap().clear();
+        self.sync_network_ids();
+        Ok(())
+    }
+
+    pub fn add_action(&mut self, action: Action) {
+        let mut action_queue= self.resources.get_mut::<ActionQueue>().unwrap();
+        action_queue.push(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::trade_handler::{TradeMessage, TradeState};
+    use crate::serializers::Problem;
+
+    fn test_server(seed: u64) -> Server {
+        let entities = concat!(env!("CARGO_MANIFEST_DIR"), "/../entities.json");
+        let prefabs = concat!(env!("CARGO_MANIFEST_DIR"), "/../prefabs.json");
+        Server::from_factory(
+            entity_factory::EntityFactory::load_from(entities),
+            PrefabLibrary::load_from(prefabs),
+            SpawnTables::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../spawn_tables.json")),
+            seed,
+        )
+        .unwrap()
+    }
+
+    fn first_display(server: &Server) -> Entity {
+        let query = <(Read<component::Tradeable>)>::query().filter(tag::<component::DisplayCabinet>());
+        query.iter_entities(&server.world).next().unwrap().0
+    }
+
+    // Displays have no negotiator, so tests can play the buyer's side by hand.
+    fn offer_first_item(server: &mut Server) -> bool {
+        let buyer = first_display(server);
+        let item = server.get_player_inventory()[0];
+        let count = stacks::quantity(&server.world, item);
+        server.try_start_trade(buyer, item, count)
+    }
+
+    #[test]
+    fn test_all() {
+        let mut server = test_server(0);
+        for _ in 0..10 {
+            server.tick();
+        }
+        assert!(offer_first_item(&mut server));
+        let messages = server.tick();
+        let request = match messages.get(0).unwrap() {
+            Message::TradeEvent(request) => {request.request},
+            _ => panic!("Expected a trade event"),
+        };
+        server.add_action(Action::TradeUpdate(TradeMessage{
+            origin: server.get_player(),
+            request,
+            state_change: TradeState::Start
+        }));
+        let messages = server.tick();
+        let trade = match messages.get(0).unwrap() {
+            Message::TradeEvent(request) => request,
+            _ => panic!("Expected a trade event"),
+        };
+        assert_eq!(trade.trade_state, TradeState::Start);
+        server.add_action(Action::TradeUpdate(TradeMessage{
+            origin: first_display(&server),
+            request,
+            state_change: TradeState::Accepted
+        }));
+        let messages = server.tick();
+        assert!(messages.is_empty());
+        server.add_action(Action::TradeUpdate(TradeMessage{
+            origin: first_display(&server),
+            request,
+            state_change: TradeState::Offer(30)
+        }));
+        let messages = server.tick();
+        let trade = match messages.get(0).unwrap() {
+            Message::TradeEvent(request) => request,
+            _ => panic!("Expected a trade event"),
+        };
+        assert_eq!(trade.trade_state, TradeState::Offer(30));
+        server.add_action(Action::TradeUpdate(TradeMessage{
+            origin: server.get_player(),
+            request,
+            state_change: TradeState::Accepted
+        }));
+        let messages = server.tick();
+        let trade = match messages.get(0).unwrap() {
+            Message::TradeEvent(request) => request,
+            _ => panic!("Expected a trade event"),
+        };
+        assert_eq!(trade.trade_state, TradeState::Final(30));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut server = test_server(3);
+        for _ in 0..5 {
+            server.tick();
+        }
+        assert!(offer_first_item(&mut server));
+        // A stack with nothing but its kind and size still has to make it into the save.
+        server.world.insert(
+            (),
+            vec![(
+                component::Prototype {
+                    id: "apple".to_string(),
+                },
+                component::Quantity { count: 4, max: 10 },
+            )],
+        );
+        let path = std::env::temp_dir().join("god-four-am-save-test.json");
+        server.save(&path).unwrap();
+
+        let mut restored = test_server(9);
+        restored.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let names = |server: &Server| {
+            let mut names: Vec<String> = server
+                .get_player_inventory()
+                .iter()
+                .map(|item| server.world.get_component::<component::Name>(*item).unwrap().name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&server), names(&restored));
+        let position = |server: &Server| {
+            server
+                .world
+                .get_component::<component::Position>(server.get_player())
+                .map(|position| *position)
+        };
+        assert_eq!(position(&server), position(&restored));
+
+        let player = restored.get_player();
+        let trade = restored
+            .resources
+            .get::<TradeHandler>()
+            .unwrap()
+            .active_trades()
+            .next()
+            .cloned()
+            .unwrap();
+        assert_eq!(trade.seller, player);
+        assert!(restored.get_player_inventory().contains(&trade.target));
+        let bare_stacks = <(Read<component::Quantity>)>::query()
+            .filter(!component::<component::Name>())
+            .iter(&restored.world)
+            .count();
+        assert_eq!(bare_stacks, 1);
+
+        // Saving leaves the generator alone, and the restored game rolls what the original would.
+        assert_eq!(
+            *server.resources.get::<CustomerSchedule>().unwrap(),
+            *restored.resources.get::<CustomerSchedule>().unwrap()
+        );
+        let roll = |server: &Server| server.resources.get_mut::<GameRng>().unwrap().gen::<u64>();
+        assert_eq!(roll(&server), roll(&restored));
+    }
+
+    #[test]
+    fn test_move_to() {
+        let mut server = test_server(7);
+        for _ in 0..2 {
+            server.tick();
+        }
+        let player = server.get_player();
+        let start: Point = (*server.world.get_component::<component::Position>(player).unwrap()).into();
+        let target = {
+            let map = server.resources.get::<Map>().unwrap();
+            let rules = server.resources.get::<MovementRules>().unwrap();
+            *crate::map::pathfinding::reachable_within(&map, &rules, start, 3, Occupancy::Avoid)
+                .last()
+                .unwrap()
+        };
+        assert!(server.try_move_to(target));
+        for _ in 0..20 {
+            server.tick();
+        }
+        let position: Point = (*server.world.get_component::<component::Position>(player).unwrap()).into();
+        assert_eq!(position, target);
+        assert!(server.world.get_component::<component::MovePlan>(player).is_none());
+    }
+
+    #[test]
+    fn test_day_cycle() {
+        let mut server = test_server(5);
+        server.tick();
+        assert_eq!(server.run_state(), RunState::Restock);
+        assert!(server.open_shop());
+        assert!(!server.open_shop());
+
+        server.pause();
+        server.tick();
+        assert_eq!(server.run_state(), RunState::Paused);
+        server.resume();
+        assert_eq!(server.run_state(), RunState::Running);
+
+        server.resources.get_mut::<ShopDay>().unwrap().closes_at_round = 0;
+        server.tick();
+        assert_eq!(server.run_state(), RunState::Closing);
+        server.tick();
+        assert_eq!(server.run_state(), RunState::EndOfDay);
+        let messages = server.tick();
+        let summary = messages.iter().find_map(|message| match message {
+            Message::DaySummary(ledger) => Some(ledger.clone()),
+            _ => None,
+        });
+        assert_eq!(summary.map(|ledger| ledger.day), Some(1));
+        assert_eq!(server.run_state(), RunState::Restock);
+        assert_eq!(server.resources.get::<ShopDay>().unwrap().day, 2);
+    }
+
+    #[test]
+    fn test_displays_are_restocked() {
+        let mut server = test_server(5);
+        server.tick();
+        let displays: Vec<Entity> = <(Read<component::Inventory>)>::query()
+            .filter(tag::<component::DisplayCabinet>())
+            .iter_entities(&server.world)
+            .map(|(entity, _)| entity)
+            .collect();
+        assert!(!displays.is_empty());
+        let stocked = |server: &Server, display: Entity| {
+            server.world.get_component::<component::Inventory>(display).unwrap().contents.len()
+        };
+        assert!(displays.iter().all(|display| stocked(&server, *display) == 1));
+
+        server
+            .world
+            .get_component_mut::<component::Inventory>(displays[0])
+            .unwrap()
+            .contents
+            .clear();
+        server.run_state = RunState::EndOfDay;
+        server.tick();
+        assert_eq!(stocked(&server, displays[0]), 1);
+        let item = server.world.get_component::<component::Inventory>(displays[0]).unwrap().contents[0];
+        assert!(server.world.get_component::<component::Prototype>(item).is_some());
+    }
+
+    #[test]
+    fn test_put_and_take_split_stacks() {
+        let mut server = test_server(5);
+        server.tick();
+        let love = server.get_player_inventory()[0];
+        server.world.get_component_mut::<component::Quantity>(love).unwrap().count = 5;
+        let display = first_display(&server);
+        for item in server.world.get_component::<component::Inventory>(display).unwrap().contents.clone() {
+            server.world.delete(item);
+        }
+        server.world.get_component_mut::<component::Inventory>(display).unwrap().contents.clear();
+
+        assert!(server.try_player_put(display, love, 2));
+        let shelved = server.world.get_component::<component::Inventory>(display).unwrap().contents.clone();
+        assert_eq!(shelved.len(), 1);
+        assert_ne!(shelved[0], love);
+        assert_eq!(stacks::quantity(&server.world, shelved[0]), 2);
+        assert_eq!(stacks::quantity(&server.world, love), 3);
+        assert!(!server.try_player_put(display, love, 4));
+
+        // With the display full, the units split off for it go straight back onto the stack.
+        server.world.get_component_mut::<component::Inventory>(display).unwrap().capacity = 1;
+        server.world.get_component_mut::<component::Quantity>(shelved[0]).unwrap().count = 10;
+        assert!(!server.try_player_put(display, love, 2));
+        assert_eq!(stacks::quantity(&server.world, love), 3);
+        assert_eq!(server.get_player_inventory().iter().filter(|item| **item == love).count(), 1);
+        server.world.get_component_mut::<component::Quantity>(shelved[0]).unwrap().count = 2;
+
+        assert!(server.try_player_take(display, shelved[0], 2));
+        assert_eq!(stacks::quantity(&server.world, love), 5);
+        assert!(server.world.get_component::<component::Inventory>(display).unwrap().contents.is_empty());
+    }
+
+    #[test]
+    fn test_preview_returns_to_the_shop() {
+        let mut server = test_server(0);
+        assert_eq!(server.run_state(), RunState::Initializing);
+        let shop_size = server.resources.get::<Map>().unwrap().size;
+        assert!(server.preview_generator("drunk"));
+        assert_eq!(server.resources.get::<Map>().unwrap().size, crate::geom::Vector::from(PREVIEW_SIZE));
+
+        // Skip to the last frame of the playback.
+        server.map_state.mapgen_index = server.map_state.mapgen_built_map.history.len();
+        std::thread::sleep(std::time::Duration::from_millis(MAPGEN_FRAME_MILLIS as u64));
+        server.tick();
+        assert_eq!(server.run_state(), RunState::Initializing);
+        assert_eq!(server.resources.get::<Map>().unwrap().size, shop_size);
+
+        server.tick();
+        assert_eq!(server.run_state(), RunState::Restock);
+        let query = <(Read<component::Tradeable>)>::query().filter(tag::<component::DisplayCabinet>());
+        assert!(query.iter(&server.world).next().is_some());
+    }
+
+    #[test]
+    fn test_stairs_to_cellar_and_back() {
+        let mut server = test_server(3);
+        server.tick();
+        server.tick();
+        let player = server.get_player();
+        let stairs = server
+            .resources
+            .get::<Levels>()
+            .unwrap()
+            .links
+            .iter()
+            .find(|link| link.target == CELLAR_DEPTH)
+            .unwrap()
+            .position;
+        let move_player = |server: &mut Server, to: Point| {
+            server
+                .world
+                .get_component_mut::<component::Position>(player)
+                .map(|mut position| {
+                    position.x = to.x;
+                    position.y = to.y;
+                });
+        };
+        let parked_count = |server: &Server| <(Read<component::Parked>)>::query().iter(&server.world).count();
+
+        move_player(&mut server, stairs);
+        server.check_level_transition();
+        assert_eq!(server.resources.get::<Map>().unwrap().depth, CELLAR_DEPTH);
+        assert!(parked_count(&server) > 0);
+        let arrival = server.resources.get::<Levels>().unwrap().arrived_at.unwrap();
+        assert_eq!(server.resources.get::<Map>().unwrap().get_type(arrival), TileType::UpStairs);
+
+        // Standing on the arrival tile doesn't bounce the player straight back up.
+        server.check_level_transition();
+        assert_eq!(server.resources.get::<Map>().unwrap().depth, CELLAR_DEPTH);
+
+        server.resources.get_mut::<Levels>().unwrap().arrived_at = None;
+        server.check_level_transition();
+        assert_eq!(server.resources.get::<Map>().unwrap().depth, SHOP_DEPTH);
+        let position: Point = (*server.world.get_component::<component::Position>(player).unwrap()).into();
+        assert_eq!(position, stairs);
+        assert!(server.resources.get::<Levels>().unwrap().is_generated(CELLAR_DEPTH));
+    }
+
+    #[test]
+    fn test_unlinked_stairs_and_parked_customers() {
+        let mut server = test_server(3);
+        server.tick();
+        server.tick();
+        let player = server.get_player();
+        server.change_level(player, SHOP_DEPTH, CELLAR_DEPTH);
+        server.change_level(player, CELLAR_DEPTH, SHOP_DEPTH);
+
+        // With the way back gone, the cellar can't be entered rather than panicking.
+        server
+            .resources
+            .get_mut::<Levels>()
+            .unwrap()
+            .links
+            .retain(|link| link.depth != CELLAR_DEPTH);
+        server.change_level(player, SHOP_DEPTH, CELLAR_DEPTH);
+        assert_eq!(server.resources.get::<Map>().unwrap().depth, SHOP_DEPTH);
+        let messages = server.tick();
+        assert!(messages.iter().any(|message| match message {
+            Message::ActionRejected(_) => true,
+            _ => false,
+        }));
+
+        // A customer left waiting on another level doesn't keep the shop open.
+        let mut command_buffer = CommandBuffer::new(&server.world);
+        let customer = server.factory.build(CUSTOMER_IDS[0], None, &mut command_buffer).unwrap();
+        command_buffer.write(&mut server.world);
+        let _ = server.world.add_component(
+            customer,
+            component::Parked {
+                depth: CELLAR_DEPTH,
+                position: (1, 1).into(),
+            },
+        );
+        server.run_state = RunState::Closing;
+        server.tick();
+        assert_eq!(server.run_state(), RunState::EndOfDay);
+    }
+
+    #[test]
+    fn test_required_ids() {
+        let entities = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../entities.json")).unwrap();
+        let factory = entity_factory::EntityFactory::parse(
+            &entities.replacen("\"id\": \"haggler\"", "\"id\": \"bargain_hunter\"", 1),
+        )
+        .unwrap();
+        let server = Server::from_factory(
+            factory,
+            PrefabLibrary::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../prefabs.json")),
+            SpawnTables::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../spawn_tables.json")),
+            0,
+        );
+        match server {
+            Err(FactoryError::Invalid(errors)) => {
+                assert_eq!(errors[0].problem, Problem::MissingId("haggler".to_string()))
+            }
+            _ => panic!("expected the missing customer to be reported"),
+        }
+    }
+
+    #[test]
+    fn test_reload_definitions() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/../entities.json");
+        let path = std::env::temp_dir().join(format!("entities-reload-{}.json", std::process::id()));
+        std::fs::copy(source, &path).unwrap();
+        let prefabs = concat!(env!("CARGO_MANIFEST_DIR"), "/../prefabs.json");
+        let mut server = Server::from_factory(
+            entity_factory::EntityFactory::load_from(&path),
+            PrefabLibrary::load_from(prefabs),
+            SpawnTables::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../spawn_tables.json")),
+            0,
+        )
+        .unwrap();
+        server.tick();
+        let player = server.get_player();
+
+        let edited = std::fs::read_to_string(&path)
+            .unwrap()
+            .replacen("\"name\": \"Player\"", "\"name\": \"Shopkeeper\"", 1);
+        std::fs::write(&path, edited).unwrap();
+        assert_eq!(server.reload_definitions(true).unwrap(), 1);
+        assert_eq!(server.world.get_component::<component::Name>(player).unwrap().name, "Shopkeeper");
+        let messages = server.tick();
+        assert!(messages.iter().any(|message| match message {
+            Message::EntitiesChanged(entities) => entities == &vec![player],
+            _ => false,
+        }));
+
+        std::fs::write(&path, "{ \"builder\": [").unwrap();
+        assert!(server.reload_definitions(true).is_err());
+        assert!(server.factory.contains("player"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_keeps_ids_in_use() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/../entities.json");
+        let path = std::env::temp_dir().join(format!("entities-in-use-{}.json", std::process::id()));
+        std::fs::copy(source, &path).unwrap();
+        let mut server = Server::from_factory(
+            entity_factory::EntityFactory::load_from(&path),
+            PrefabLibrary::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../prefabs.json")),
+            SpawnTables::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/../spawn_tables.json")),
+            0,
+        )
+        .unwrap();
+        server.tick();
+
+        // The spawn tables still roll apples, so a file without them is refused.
+        let edited = std::fs::read_to_string(&path)
+            .unwrap()
+            .replacen("\"id\": \"apple\"", "\"id\": \"crab_apple\"", 1);
+        std::fs::write(&path, edited).unwrap();
+        assert!(server.reload_definitions(false).is_err());
+        assert!(server.factory.contains("apple"));
+        assert!(!server.factory.contains("crab_apple"));
+        std::fs::remove_file(&path).unwrap();
+
+        let displays: Vec<Entity> = <(Read<component::Inventory>)>::query()
+            .filter(tag::<component::DisplayCabinet>())
+            .iter_entities(&server.world)
+            .map(|(entity, _)| entity)
+            .collect();
+        for display in displays.iter() {
+            server.world.get_component_mut::<component::Inventory>(*display).unwrap().contents.clear();
+        }
+        server.restock_displays();
+        assert!(displays.iter().any(|display| {
+            !server.world.get_component::<component::Inventory>(*display).unwrap().contents.is_empty()
+        }));
+    }
+}
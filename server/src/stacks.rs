@@ -0,0 +1,241 @@
+use crate::component::{Inventory, Name, Prototype, Quantity, Renderable};
+use crate::serializers::entity_factory::EntityFactory;
+use legion::prelude::*;
+
+/// The kind and size of a stackable item, or `None` if it doesn't stack. Items stack with others
+/// built from the same prototype.
+pub fn stack_of(world: &World, item: Entity) -> Option<(String, Quantity)> {
+    let quantity = *world.get_component::<Quantity>(item)?;
+    let prototype = world.get_component::<Prototype>(item)?;
+    Some((prototype.id.clone(), quantity))
+}
+
+/// Units `item` stands for; anything that doesn't stack is a single unit.
+pub fn quantity(world: &World, item: Entity) -> u32 {
+    world.get_component::<Quantity>(item).map_or(1, |quantity| quantity.count)
+}
+
+/// Whether `count` units of `kind` fit in `inventory`, either in a free slot once `reserved`
+/// slots are spoken for or by topping up the stacks of that kind already there. Takes the
+/// stack lookup as a closure so systems can answer it from their own view of the world.
+pub fn fits<F>(inventory: &Inventory, reserved: usize, kind: Option<&str>, count: u32, stack_of: F) -> bool
+where
+    F: Fn(Entity) -> Option<(String, Quantity)>,
+{
+    if inventory.contents.len() + reserved < inventory.capacity as usize {
+        return true;
+    }
+    let kind = match kind {
+        Some(kind) => kind,
+        None => return false,
+    };
+    let room: u32 = inventory
+        .contents
+        .iter()
+        .filter_map(|item| stack_of(*item))
+        .filter(|(other, _)| other == kind)
+        .map(|(_, quantity)| quantity.room())
+        .sum();
+    room >= count
+}
+
+pub fn can_hold(world: &World, container: Entity, item: Entity) -> bool {
+    let inventory = match world.get_component::<Inventory>(container) {
+        Some(inventory) => inventory,
+        None => return false,
+    };
+    let stack = stack_of(world, item);
+    let count = stack.as_ref().map_or(1, |(_, quantity)| quantity.count);
+    let kind = stack.as_ref().map(|(kind, _)| kind.as_str());
+    fits(&inventory, 0, kind, count, |other| stack_of(world, other))
+}
+
+/// Moves `count` units of `item` out of `container`. A whole stack leaves as it is, while part
+/// of one is split off into a new entity built from the same definition. Returns whichever
+/// entity holds the units taken, or `None` if there aren't enough or the stack can't be split
+/// because its definition is gone.
+pub fn take(
+    world: &mut World,
+    factory: &EntityFactory,
+    container: Entity,
+    item: Entity,
+    count: u32,
+) -> Option<Entity> {
+    if !world.get_component::<Inventory>(container)?.contents.contains(&item) {
+        return None;
+    }
+    let available = quantity(world, item);
+    if count == 0 || count > available {
+        return None;
+    }
+    if count == available {
+        world
+            .get_component_mut::<Inventory>(container)?
+            .contents
+            .remove_item(&item);
+        return Some(item);
+    }
+    split(world, factory, item, count)
+}
+
+fn split(world: &mut World, factory: &EntityFactory, item: Entity, count: u32) -> Option<Entity> {
+    let id = world.get_component::<Prototype>(item)?.id.clone();
+    let max = world.get_component::<Quantity>(item)?.max;
+    let mut command_buffer = CommandBuffer::new(world);
+    let part = factory.build(&id, None, &mut command_buffer)?;
+    command_buffer.write(world);
+    // The definition may have been reloaded since the stack was built, so the part keeps
+    // looking like the rest of it.
+    let name = world.get_component::<Name>(item).map(|name| name.as_ref().clone());
+    let renderable = world.get_component::<Renderable>(item).map(|renderable| *renderable);
+    if let (Some(name), Some(mut current)) = (name, world.get_component_mut::<Name>(part)) {
+        *current = name;
+    }
+    if let (Some(renderable), Some(mut current)) = (renderable, world.get_component_mut::<Renderable>(part)) {
+        *current = renderable;
+    }
+    world.get_component_mut::<Quantity>(item).unwrap().count -= count;
+    let quantity = Quantity { count, max };
+    if world.get_component::<Quantity>(part).is_some() {
+        *world.get_component_mut::<Quantity>(part).unwrap() = quantity;
+    } else {
+        let _ = world.add_component(part, quantity);
+    }
+    Some(part)
+}
+
+/// Adds `item` to `container`, topping up stacks of the same kind before it takes a slot of its
+/// own. If every unit merged into existing stacks, `item` is deleted. Leaves everything as it
+/// was and returns false if there isn't room.
+pub fn put(world: &mut World, container: Entity, item: Entity) -> bool {
+    if !can_hold(world, container, item) {
+        return false;
+    }
+    if let Some((kind, quantity)) = stack_of(world, item) {
+        let mut remaining = quantity.count;
+        let contents = world.get_component::<Inventory>(container).unwrap().contents.clone();
+        for other in contents {
+            if remaining == 0 {
+                break;
+            }
+            match stack_of(world, other) {
+                Some((other_kind, _)) if other_kind == kind => {}
+                _ => continue,
+            }
+            let mut other_quantity = world.get_component_mut::<Quantity>(other).unwrap();
+            let moved = other_quantity.room().min(remaining);
+            other_quantity.count += moved;
+            remaining -= moved;
+        }
+        if remaining == 0 {
+            world.delete(item);
+            return true;
+        }
+        world.get_component_mut::<Quantity>(item).unwrap().count = remaining;
+    }
+    world
+        .get_component_mut::<Inventory>(container)
+        .unwrap()
+        .contents
+        .push(item);
+    true
+}
+
+/// Returns units taken by `take` to the container they came from. They merge back into what's
+/// left of their stack or reuse the slot they freed, so this only overfills the container if
+/// something else changed it in between, and it never loses them.
+pub fn put_back(world: &mut World, container: Entity, item: Entity) {
+    if put(world, container, item) {
+        return;
+    }
+    if let Some(mut inventory) = world.get_component_mut::<Inventory>(container) {
+        inventory.contents.push(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Value;
+
+    const APPLES: &str = r##"{ "builder": [
+        { "id": "apple", "name": "Apple", "renderable": { "glyph": { "ch": "a", "render_order": 3 } },
+          "value": { "base": 4, "rarity": "common" }, "stack": { "max": 10 } },
+        { "id": "pear", "name": "Pear", "renderable": { "glyph": { "ch": "p", "render_order": 3 } } }
+    ] }"##;
+
+    fn build(world: &mut World, factory: &EntityFactory, id: &str, count: u32) -> Entity {
+        let mut command_buffer = CommandBuffer::new(world);
+        let item = factory.build(id, None, &mut command_buffer).unwrap();
+        command_buffer.write(world);
+        if let Some(mut quantity) = world.get_component_mut::<Quantity>(item) {
+            quantity.count = count;
+        }
+        item
+    }
+
+    fn container(world: &mut World, capacity: u8) -> Entity {
+        world.insert(
+            (),
+            vec![(Inventory {
+                contents: vec![],
+                capacity,
+            },)],
+        )[0]
+    }
+
+    #[test]
+    fn test_put_merges_and_take_splits() {
+        let factory = EntityFactory::parse(APPLES).unwrap();
+        let mut world = Universe::new().create_world();
+        let crate_entity = container(&mut world, 1);
+        let first = build(&mut world, &factory, "apple", 6);
+        assert!(put(&mut world, crate_entity, first));
+
+        // Four more top the stack up to its max; a fifth has nowhere to go.
+        let second = build(&mut world, &factory, "apple", 4);
+        assert!(put(&mut world, crate_entity, second));
+        assert!(!world.is_alive(second));
+        assert_eq!(quantity(&world, first), 10);
+        let third = build(&mut world, &factory, "apple", 1);
+        assert!(!put(&mut world, crate_entity, third));
+        assert_eq!(quantity(&world, third), 1);
+
+        let part = take(&mut world, &factory, crate_entity, first, 3).unwrap();
+        assert_ne!(part, first);
+        assert_eq!(quantity(&world, part), 3);
+        assert_eq!(quantity(&world, first), 7);
+        // The part is built from the definition, so it has everything the stack has.
+        assert_eq!(
+            world.get_component::<Value>(part).map(|value| *value),
+            world.get_component::<Value>(first).map(|value| *value)
+        );
+        assert_eq!(world.get_component::<Name>(part).unwrap().name, "Apple");
+        assert!(take(&mut world, &factory, crate_entity, first, 8).is_none());
+        assert_eq!(take(&mut world, &factory, crate_entity, first, 7), Some(first));
+        assert!(world.get_component::<Inventory>(crate_entity).unwrap().contents.is_empty());
+    }
+
+    #[test]
+    fn test_put_back_never_loses_units() {
+        let factory = EntityFactory::parse(APPLES).unwrap();
+        let mut world = Universe::new().create_world();
+        let crate_entity = container(&mut world, 1);
+        let apples = build(&mut world, &factory, "apple", 5);
+        assert!(put(&mut world, crate_entity, apples));
+        let part = take(&mut world, &factory, crate_entity, apples, 2).unwrap();
+        put_back(&mut world, crate_entity, part);
+        assert_eq!(quantity(&world, apples), 5);
+        assert!(!world.is_alive(part));
+
+        // A pear took the freed slot in between, so the apples can't be put back the usual way.
+        let taken = take(&mut world, &factory, crate_entity, apples, 5).unwrap();
+        let pear = build(&mut world, &factory, "pear", 1);
+        assert!(put(&mut world, crate_entity, pear));
+        put_back(&mut world, crate_entity, taken);
+        assert_eq!(
+            world.get_component::<Inventory>(crate_entity).unwrap().contents,
+            vec![pear, taken]
+        );
+    }
+}
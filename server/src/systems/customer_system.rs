@@ -0,0 +1,376 @@
+use crate::component::{
+    ActiveTurn, Customer, CustomerState, DisplayCabinet, Inventory, Player, Position, Quantity,
+    Tradeable, TurnState,
+};
+use crate::geom::Point;
+use crate::map::pathfinding::{a_star, Occupancy};
+use crate::map::Map;
+use crate::message::{Action, Message};
+use crate::resources::action_queue::ActionQueue;
+use crate::resources::message_queue::MessageQueue;
+use crate::resources::movement_rules::MovementRules;
+use crate::resources::trade_handler::{TradeHandler, TradeMessage, TradeState};
+use legion::prelude::*;
+
+// The most units of one stack a customer asks for at once.
+const MAX_UNITS_WANTED: u32 = 3;
+// How long a leaving customer waits for a way out before giving up and vanishing.
+const MAX_STUCK_TURNS: u32 = 10;
+
+struct Cabinet {
+    entity: Entity,
+    position: Point,
+    contents: Vec<Entity>,
+}
+
+fn choose_cabinet(rules: &MovementRules, cabinets: &[Cabinet], position: Point) -> Option<Entity> {
+    cabinets
+        .iter()
+        .filter(|cabinet| !cabinet.contents.is_empty())
+        .min_by_key(|cabinet| rules.distance(cabinet.position, position))
+        .map(|cabinet| cabinet.entity)
+}
+
+fn despawn(command_buffer: &mut CommandBuffer, entity: Entity) {
+    command_buffer.exec_mut(move |world| {
+        let contents = world
+            .get_component::<Inventory>(entity)
+            .map(|inventory| inventory.contents.clone())
+            .unwrap_or_default();
+        for item in contents {
+            world.delete(item);
+        }
+        world.delete(entity);
+    });
+}
+
+fn step_towards(map: &Map, rules: &MovementRules, from: Point, to: Point) -> Option<Point> {
+    a_star(map, rules, from, to, Occupancy::Avoid).and_then(|path| path.first().cloned())
+}
+
+pub fn customer_system() -> Box<dyn Schedulable> {
+    SystemBuilder::new("customer_system")
+        .read_resource::<Map>()
+        .write_resource::<TradeHandler>()
+        .write_resource::<MessageQueue>()
+        .write_resource::<ActionQueue>()
+        .read_resource::<MovementRules>()
+        .read_component::<Quantity>()
+        .with_query(<(Read<Customer>, Read<Position>, Read<Tradeable>, Read<ActiveTurn>)>::query())
+        .with_query(<(Read<Position>, Read<Inventory>)>::query().filter(tag::<DisplayCabinet>()))
+        .with_query(<Read<Tradeable>>::query().filter(tag::<Player>()))
+        .build(
+            move |command_buffer,
+                  world,
+                  (map, trade_handler, message_queue, action_queue, rules),
+                  (customer_query, cabinet_query, player_query)| {
+                let map: &Map = map;
+                let rules: &MovementRules = rules;
+                let trade_handler: &mut TradeHandler = trade_handler;
+                let message_queue: &mut MessageQueue = message_queue;
+                let action_queue: &mut ActionQueue = action_queue;
+
+                let active: Vec<_> = customer_query
+                    .iter_entities(world)
+                    .filter(|(_, (_, _, _, turn))| turn.state == TurnState::PENDING)
+                    .map(|(entity, (customer, position, tradeable, _))| {
+                        let position: Point = (*position).into();
+                        (entity, *customer, position, tradeable.request)
+                    })
+                    .collect();
+                if active.is_empty() {
+                    return;
+                }
+
+                let cabinets: Vec<Cabinet> = cabinet_query
+                    .iter_entities(world)
+                    .map(|(entity, (position, inventory))| Cabinet {
+                        entity,
+                        position: (*position).into(),
+                        contents: inventory.contents.clone(),
+                    })
+                    .collect();
+                let (player, player_busy) = match player_query.iter_entities(world).next() {
+                    Some((entity, tradeable)) => (entity, tradeable.request.is_some()),
+                    None => return,
+                };
+
+                for (entity, mut customer, position, request) in active {
+                    let mut next_position = position;
+                    match customer.state {
+                        CustomerState::Entering => {
+                            customer.target = choose_cabinet(rules, &cabinets, position);
+                            customer.state = match customer.target {
+                                Some(_) => CustomerState::Browsing,
+                                None => CustomerState::Leaving,
+                            };
+                        }
+                        CustomerState::Browsing => {
+                            let cabinet = customer
+                                .target
+                                .and_then(|target| cabinets.iter().find(|c| c.entity == target));
+                            match cabinet {
+                                None => customer.state = CustomerState::Leaving,
+                                Some(cabinet) if rules.distance(position, cabinet.position) <= 1 => {
+                                    match cabinet.contents.first() {
+                                        None => {
+                                            customer.target = choose_cabinet(rules, &cabinets, position);
+                                            if customer.target.is_none() {
+                                                customer.state = CustomerState::Leaving;
+                                            }
+                                        }
+                                        Some(_) if player_busy => {
+                                            customer.patience = customer.patience.saturating_sub(1);
+                                        }
+                                        Some(item) => {
+                                            let count = world
+                                                .get_component::<Quantity>(*item)
+                                                .map_or(1, |quantity| quantity.count.min(MAX_UNITS_WANTED));
+                                            let request =
+                                                trade_handler.start(*item, count, entity, player, entity);
+                                            command_buffer.exec_mut(move |world| {
+                                                world.get_component_mut::<Tradeable>(entity).map(
+                                                    |mut tradeable| tradeable.request = Some(request),
+                                                );
+                                                world.get_component_mut::<Tradeable>(player).map(
+                                                    |mut tradeable| tradeable.request = Some(request),
+                                                );
+                                            });
+                                            trade_handler
+                                                .get_trade(request)
+                                                .map(|trade| message_queue.push(Message::TradeEvent(trade)));
+                                            customer.state = CustomerState::Trading;
+                                        }
+                                    }
+                                }
+                                Some(cabinet) => match step_towards(map, rules, position, cabinet.position) {
+                                    Some(step) => next_position = step,
+                                    None => customer.patience = customer.patience.saturating_sub(1),
+                                },
+                            }
+                            if customer.state == CustomerState::Browsing && customer.patience == 0 {
+                                customer.state = CustomerState::Leaving;
+                            }
+                        }
+                        CustomerState::Trading => match request {
+                            None => customer.state = CustomerState::Leaving,
+                            Some(request) => {
+                                customer.patience = customer.patience.saturating_sub(1);
+                                if customer.patience == 0 {
+                                    action_queue.push_future(Action::TradeUpdate(TradeMessage {
+                                        origin: entity,
+                                        request,
+                                        state_change: TradeState::Rejected,
+                                    }));
+                                }
+                            }
+                        },
+                        CustomerState::Leaving => {
+                            if position == customer.exit {
+                                despawn(command_buffer, entity);
+                                continue;
+                            }
+                            match step_towards(map, rules, position, customer.exit) {
+                                Some(step) => {
+                                    next_position = step;
+                                    customer.stuck = 0;
+                                }
+                                None => {
+                                    customer.stuck += 1;
+                                    if customer.stuck >= MAX_STUCK_TURNS {
+                                        despawn(command_buffer, entity);
+                                        continue;
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    command_buffer.exec_mut(move |world| {
+                        world
+                            .get_component_mut::<Customer>(entity)
+                            .map(|mut current| *current = customer);
+                        world.get_component_mut::<Position>(entity).map(|mut current| {
+                            current.x = next_position.x;
+                            current.y = next_position.y;
+                        });
+                        world
+                            .get_component_mut::<ActiveTurn>(entity)
+                            .map(|mut turn| turn.state = TurnState::DONE);
+                    });
+                }
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Shop {
+        world: World,
+        resources: Resources,
+        player: Entity,
+        cabinet: Entity,
+    }
+
+    // A walled 10x6 room with the player in one corner and a cabinet holding five apples.
+    fn shop() -> Shop {
+        let mut map = Map::new((10, 6), 0);
+        for x in 1..9 {
+            for y in 1..5 {
+                map.set_type(Point::new(x, y), crate::map::TileType::Floor);
+            }
+        }
+        map.refresh_blocked();
+        let mut resources = Resources::default();
+        resources.insert(map);
+        resources.insert(TradeHandler::new());
+        resources.insert(MessageQueue::new());
+        resources.insert(ActionQueue::new());
+        resources.insert(MovementRules::default());
+
+        let mut world = Universe::new().create_world();
+        let player = world.insert((Player,), vec![(Position { x: 1, y: 1 }, Tradeable { request: None })])[0];
+        let apples = world.insert((), vec![(Quantity { count: 5, max: 10 },)])[0];
+        let cabinet = world.insert(
+            (DisplayCabinet,),
+            vec![(
+                Position { x: 8, y: 4 },
+                Inventory {
+                    contents: vec![apples],
+                    capacity: 1,
+                },
+            )],
+        )[0];
+        Shop {
+            world,
+            resources,
+            player,
+            cabinet,
+        }
+    }
+
+    fn customer(shop: &mut Shop, state: CustomerState, at: Point) -> Entity {
+        let customer = Customer {
+            state,
+            target: Some(shop.cabinet),
+            exit: Point::new(1, 4),
+            patience: 5,
+            stuck: 0,
+        };
+        shop.world.insert(
+            (),
+            vec![(
+                customer,
+                Position { x: at.x, y: at.y },
+                Tradeable { request: None },
+                ActiveTurn {
+                    state: TurnState::PENDING,
+                },
+            )],
+        )[0]
+    }
+
+    fn take_turn(shop: &mut Shop, entity: Entity) -> Option<Customer> {
+        shop.world
+            .get_component_mut::<ActiveTurn>(entity)
+            .map(|mut turn| turn.state = TurnState::PENDING);
+        let mut schedule = Schedule::builder().add_system(customer_system()).build();
+        schedule.execute(&mut shop.world, &mut shop.resources);
+        shop.world.get_component::<Customer>(entity).map(|customer| *customer)
+    }
+
+    #[test]
+    fn test_entering_browses_a_stocked_cabinet() {
+        let mut shop = shop();
+        let entity = customer(&mut shop, CustomerState::Entering, Point::new(1, 4));
+        let state = take_turn(&mut shop, entity).unwrap();
+        assert_eq!(state.state, CustomerState::Browsing);
+        assert_eq!(state.target, Some(shop.cabinet));
+    }
+
+    #[test]
+    fn test_entering_leaves_an_empty_shop() {
+        let mut shop = shop();
+        let cabinet = shop.cabinet;
+        shop.world.get_component_mut::<Inventory>(cabinet).unwrap().contents.clear();
+        let entity = customer(&mut shop, CustomerState::Entering, Point::new(1, 4));
+        assert_eq!(take_turn(&mut shop, entity).unwrap().state, CustomerState::Leaving);
+    }
+
+    #[test]
+    fn test_browsing_walks_up_and_asks_for_part_of_a_stack() {
+        let mut shop = shop();
+        let entity = customer(&mut shop, CustomerState::Browsing, Point::new(5, 4));
+        assert_eq!(take_turn(&mut shop, entity).unwrap().state, CustomerState::Browsing);
+        assert_eq!(*shop.world.get_component::<Position>(entity).unwrap(), Position { x: 6, y: 4 });
+
+        take_turn(&mut shop, entity);
+        assert_eq!(take_turn(&mut shop, entity).unwrap().state, CustomerState::Trading);
+        let trade = shop.resources.get::<TradeHandler>().unwrap().active_trades().next().cloned().unwrap();
+        assert_eq!((trade.buyer, trade.seller, trade.count), (entity, shop.player, MAX_UNITS_WANTED));
+        let player = shop.player;
+        assert!(shop.world.get_component::<Tradeable>(player).unwrap().request.is_some());
+    }
+
+    #[test]
+    fn test_browsing_gives_up_when_patience_runs_out() {
+        let mut shop = shop();
+        let player = shop.player;
+        // The player is busy with someone else, so the customer can only wait.
+        shop.world.get_component_mut::<Tradeable>(player).unwrap().request = Some(
+            shop.resources.get_mut::<TradeHandler>().unwrap().start(shop.cabinet, 1, player, player, player),
+        );
+        let entity = customer(&mut shop, CustomerState::Browsing, Point::new(7, 4));
+        shop.world.get_component_mut::<Customer>(entity).unwrap().patience = 1;
+        assert_eq!(take_turn(&mut shop, entity).unwrap().state, CustomerState::Leaving);
+    }
+
+    #[test]
+    fn test_trading_rejects_when_patience_runs_out() {
+        let mut shop = shop();
+        let entity = customer(&mut shop, CustomerState::Trading, Point::new(7, 4));
+        take_turn(&mut shop, entity);
+        assert_eq!(take_turn(&mut shop, entity).unwrap().state, CustomerState::Leaving);
+
+        let player = shop.player;
+        let request = shop.resources.get_mut::<TradeHandler>().unwrap().start(shop.cabinet, 1, entity, player, entity);
+        let other = customer(&mut shop, CustomerState::Trading, Point::new(6, 4));
+        shop.world.get_component_mut::<Tradeable>(other).unwrap().request = Some(request);
+        shop.world.get_component_mut::<Customer>(other).unwrap().patience = 1;
+        assert_eq!(take_turn(&mut shop, other).unwrap().state, CustomerState::Trading);
+        let mut action_queue = shop.resources.get_mut::<ActionQueue>().unwrap();
+        action_queue.step();
+        assert!(action_queue.get_actions().iter().any(|action| match action {
+            Action::TradeUpdate(message) => message.state_change == TradeState::Rejected,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_leaving_walks_out_and_despawns() {
+        let mut shop = shop();
+        let entity = customer(&mut shop, CustomerState::Leaving, Point::new(1, 3));
+        assert!(take_turn(&mut shop, entity).is_some());
+        assert!(take_turn(&mut shop, entity).is_none());
+        assert!(!shop.world.is_alive(entity));
+    }
+
+    #[test]
+    fn test_leaving_gives_up_when_walled_in() {
+        let mut shop = shop();
+        {
+            let mut map = shop.resources.get_mut::<Map>().unwrap();
+            for (x, y) in [(4, 3), (3, 4), (5, 4)].iter() {
+                map.set_type(Point::new(*x, *y), crate::map::TileType::Wall);
+            }
+            map.refresh_blocked();
+        }
+        let entity = customer(&mut shop, CustomerState::Leaving, Point::new(4, 4));
+        for _ in 1..MAX_STUCK_TURNS {
+            assert!(take_turn(&mut shop, entity).is_some());
+        }
+        assert!(take_turn(&mut shop, entity).is_none());
+    }
+}
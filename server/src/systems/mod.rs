@@ -0,0 +1,8 @@
+pub mod index_system;
+pub mod turn_system;
+pub mod trade_system;
+pub mod transaction_system;
+pub mod customer_system;
+pub mod negotiation_system;
+pub mod visibility_system;
+pub mod movement_system;
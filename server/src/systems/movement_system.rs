@@ -0,0 +1,70 @@
+use crate::component::{ActiveTurn, MovePlan, Position, Tradeable, TurnState};
+use crate::geom::Point;
+use crate::map::Map;
+use crate::message::Message;
+use crate::resources::message_queue::MessageQueue;
+use crate::resources::movement_rules::MovementRules;
+use legion::prelude::*;
+
+pub fn movement_system() -> Box<dyn Schedulable> {
+    SystemBuilder::new("movement_system")
+        .read_resource::<Map>()
+        .write_resource::<MessageQueue>()
+        .read_resource::<MovementRules>()
+        .read_component::<Tradeable>()
+        .with_query(<(Read<MovePlan>, Read<Position>, Read<ActiveTurn>)>::query())
+        .build(move |command_buffer, world, (map, message_queue, rules), plan_query| {
+            let map: &Map = map;
+            let rules: &MovementRules = rules;
+            let message_queue: &mut MessageQueue = message_queue;
+            let active: Vec<_> = plan_query
+                .iter_entities(world)
+                .filter(|(_, (_, _, turn))| turn.state == TurnState::PENDING)
+                .map(|(entity, (plan, position, _))| {
+                    let position: Point = (*position).into();
+                    (entity, plan.steps.clone(), position)
+                })
+                .collect();
+
+            for (entity, mut steps, position) in active {
+                let trading = world
+                    .get_component::<Tradeable>(entity)
+                    .map_or(false, |tradeable| tradeable.request.is_some());
+                if trading {
+                    command_buffer.remove_component::<MovePlan>(entity);
+                    continue;
+                }
+                let next = match steps.first() {
+                    Some(next) => *next,
+                    None => {
+                        command_buffer.remove_component::<MovePlan>(entity);
+                        continue;
+                    }
+                };
+                if !rules.can_step(map, position, next)
+                    || map.tile_content[map.point_to_index(next)].is_some()
+                {
+                    message_queue.push(Message::ActionRejected("The way is blocked.".to_string()));
+                    command_buffer.remove_component::<MovePlan>(entity);
+                    continue;
+                }
+                steps.remove(0);
+                command_buffer.exec_mut(move |world| {
+                    world.get_component_mut::<Position>(entity).map(|mut current| {
+                        current.x = next.x;
+                        current.y = next.y;
+                    });
+                    world
+                        .get_component_mut::<ActiveTurn>(entity)
+                        .map(|mut turn| turn.state = TurnState::DONE);
+                    if steps.is_empty() {
+                        let _ = world.remove_component::<MovePlan>(entity);
+                    } else {
+                        world
+                            .get_component_mut::<MovePlan>(entity)
+                            .map(|mut plan| plan.steps = steps);
+                    }
+                });
+            }
+        })
+}
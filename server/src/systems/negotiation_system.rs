@@ -0,0 +1,75 @@
+use crate::component::{Negotiator, Prototype, Value, Wallet};
+use crate::resources::market::MarketDemand;
+use crate::message::Action;
+use crate::resources::action_queue::ActionQueue;
+use crate::resources::negotiation::{NegotiationContext, NegotiationPolicies};
+use crate::resources::trade_handler::{TradeHandler, TradeMessage, TradeState};
+use legion::prelude::*;
+
+// What buyers assume an item is worth when it has no Value of its own.
+const FALLBACK_VALUE: u32 = 20;
+
+pub fn negotiation_system() -> Box<dyn Schedulable> {
+    SystemBuilder::new("negotiation_system")
+        .read_resource::<TradeHandler>()
+        .read_resource::<NegotiationPolicies>()
+        .read_resource::<MarketDemand>()
+        .write_resource::<ActionQueue>()
+        .read_component::<Negotiator>()
+        .read_component::<Wallet>()
+        .read_component::<Value>()
+        .read_component::<Prototype>()
+        .build(move |_, world, (trade_handler, policies, market, action_queue), _| {
+            let trade_handler: &TradeHandler = trade_handler;
+            let policies: &NegotiationPolicies = policies;
+            let market: &MarketDemand = market;
+            let action_queue: &mut ActionQueue = action_queue;
+            for trade in trade_handler.active_trades() {
+                if trade.last_response == trade.buyer {
+                    continue;
+                }
+                let asking = match trade.trade_state {
+                    TradeState::Start => None,
+                    TradeState::CounterOffer(value) => Some(value),
+                    _ => continue,
+                };
+                let negotiator = match world.get_component::<Negotiator>(trade.buyer) {
+                    Some(negotiator) => negotiator.as_ref().clone(),
+                    None => continue,
+                };
+                let policy = match policies.get(&negotiator.policy) {
+                    Some(policy) => policy,
+                    None => continue,
+                };
+                let unit_value = match (
+                    world.get_component::<Value>(trade.target),
+                    world.get_component::<Prototype>(trade.target),
+                ) {
+                    (Some(value), Some(prototype)) => market.price(&prototype.id, &value),
+                    (Some(value), None) => market.price("", &value),
+                    _ => FALLBACK_VALUE,
+                };
+                let base_value = unit_value * trade.count.max(1);
+                let context = NegotiationContext {
+                    base_value,
+                    patience: negotiator.patience,
+                    rounds: trade.rounds,
+                    asking,
+                };
+                // Whatever the personality, nobody agrees to a price they can't pay.
+                let budget = world.get_component::<Wallet>(trade.buyer).map(|wallet| wallet.gold);
+                let state_change = match (policy.respond(&context), budget) {
+                    (TradeState::Offer(value), Some(budget)) => TradeState::Offer(value.min(budget)),
+                    (TradeState::Accepted, Some(budget)) if asking.map_or(false, |asking| asking > budget) => {
+                        TradeState::Rejected
+                    }
+                    (state_change, _) => state_change,
+                };
+                action_queue.push(Action::TradeUpdate(TradeMessage {
+                    origin: trade.buyer,
+                    request: trade.request,
+                    state_change,
+                }));
+            }
+        })
+}
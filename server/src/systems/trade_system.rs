@@ -1,8 +1,8 @@
 
 use legion::prelude::*;
-use crate::server::resources::trade_handler::{TradeHandler, TradeState};
-use crate::server::resources::message_queue::MessageQueue;
-use crate::server::resources::action_queue::ActionQueue;
+use crate::resources::trade_handler::{TradeHandler, TradeState};
+use crate::resources::message_queue::MessageQueue;
+use crate::resources::action_queue::ActionQueue;
 use crate::message::{Action, Message};
 use crate::component::Tradeable;
 use crate::message::Action::Transaction;
@@ -28,6 +28,7 @@ pub fn trade_system() -> Box<dyn Schedulable> {
                                         source: trade.seller,
                                         target: trade.buyer,
                                         object: trade.target,
+                                        count: trade.count,
                                         value: val.clone()
                                     }),
                                     _ => {}
@@ -0,0 +1,143 @@
+use legion::prelude::*;
+use crate::resources::message_queue::MessageQueue;
+use crate::resources::action_queue::ActionQueue;
+use crate::message::{Action, Message};
+use crate::component::{Inventory, Prototype, Wallet};
+use crate::resources::market::MarketDemand;
+use crate::resources::shop_day::ShopDay;
+use crate::serializers::entity_factory::EntityFactory;
+use crate::stacks;
+
+
+/// Settles the trades that finished last tick. This needs the whole world, to split and merge
+/// stacks, as well as the ledger and market, so it runs just ahead of the schedule rather than
+/// in it. A sale is only recorded once the item and gold have both changed hands.
+pub fn settle_transactions(world: &mut World, resources: &mut Resources, factory: &EntityFactory) {
+    let actions = resources.get::<ActionQueue>().unwrap().get_actions();
+    let mut message_queue = resources.get_mut::<MessageQueue>().unwrap();
+    let mut market = resources.get_mut::<MarketDemand>().unwrap();
+    let mut shop_day = resources.get_mut::<ShopDay>().unwrap();
+    for action in actions {
+        match action {
+            Action::Transaction {
+                source,
+                target,
+                object,
+                count,
+                value
+            } => {
+                // Sellers trade stock straight out of their display cabinets, so the
+                // object isn't necessarily in the seller's own inventory.
+                let query = <(Read<Inventory>)>::query();
+                let holder = query
+                    .iter_entities(world)
+                    .find(|(_, inv)| inv.contents.contains(&object))
+                    .map(|(entity, _)| entity);
+                // Read up front, since units merged into the buyer's stack stop existing.
+                let kind = world.get_component::<Prototype>(object).map(|prototype| prototype.id.clone());
+                let units = holder.and_then(|holder| stacks::take(world, factory, holder, object, count));
+                let (holder, units) = match (holder, units) {
+                    (Some(holder), Some(units)) => (holder, units),
+                    _ => {
+                        message_queue.push(Message::LogEvent("Transaction failed: item is no longer available".to_string()));
+                        continue;
+                    }
+                };
+                let funds = world.get_component::<Wallet>(target).map_or(0, |wallet| wallet.gold);
+                if funds < value {
+                    stacks::put_back(world, holder, units);
+                    message_queue.push(Message::LogEvent(format!("Transaction failed: buyer cannot afford {} gold", value)));
+                    shop_day.record_failed_sale();
+                    continue;
+                }
+                if !stacks::put(world, target, units) {
+                    stacks::put_back(world, holder, units);
+                    message_queue.push(Message::ActionRejected("The buyer can't carry any more.".to_string()));
+                    shop_day.record_failed_sale();
+                    continue;
+                }
+                world
+                    .get_component_mut::<Wallet>(target)
+                    .map(|mut wallet| wallet.gold -= value);
+                world
+                    .get_component_mut::<Wallet>(source)
+                    .map(|mut wallet| wallet.gold += value);
+                shop_day.record_sale(value);
+                if let Some(kind) = kind {
+                    for _ in 0..count {
+                        market.record_sale(&kind);
+                    }
+                }
+                message_queue.push(Message::LogEvent("Committing transaction".to_string()));
+            },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Quantity;
+
+    const PRODUCE: &str = r##"{ "builder": [
+        { "id": "apple", "name": "Apple", "renderable": { "glyph": { "ch": "a", "render_order": 3 } }, "stack": { "max": 10 } },
+        { "id": "pear", "name": "Pear", "renderable": { "glyph": { "ch": "p", "render_order": 3 } }, "stack": { "max": 10 } }
+    ] }"##;
+
+    fn item(world: &mut World, factory: &EntityFactory, id: &str, count: u32) -> Entity {
+        let mut command_buffer = CommandBuffer::new(world);
+        let item = factory.build(id, None, &mut command_buffer).unwrap();
+        command_buffer.write(world);
+        world.get_component_mut::<Quantity>(item).unwrap().count = count;
+        item
+    }
+
+    fn trader(world: &mut World, contents: Vec<Entity>, gold: u32) -> Entity {
+        world.insert((), vec![(Inventory { contents, capacity: 1 }, Wallet { gold })])[0]
+    }
+
+    fn settle(world: &mut World, factory: &EntityFactory, transaction: Action) -> Resources {
+        let mut resources = Resources::default();
+        let mut action_queue = ActionQueue::new();
+        action_queue.push(transaction);
+        resources.insert(action_queue);
+        resources.insert(MessageQueue::new());
+        resources.insert(MarketDemand::new());
+        resources.insert(ShopDay::new());
+        settle_transactions(world, &mut resources, factory);
+        resources
+    }
+
+    #[test]
+    fn test_sale_moves_units_and_gold() {
+        let factory = EntityFactory::parse(PRODUCE).unwrap();
+        let mut world = Universe::new().create_world();
+        let apples = item(&mut world, &factory, "apple", 5);
+        let seller = trader(&mut world, vec![apples], 0);
+        let buyer = trader(&mut world, vec![], 50);
+        let resources = settle(&mut world, &factory, Action::Transaction { source: seller, target: buyer, object: apples, count: 2, value: 20 });
+        assert_eq!(stacks::quantity(&world, apples), 3);
+        let bought = world.get_component::<Inventory>(buyer).unwrap().contents[0];
+        assert_eq!(stacks::quantity(&world, bought), 2);
+        assert_eq!(world.get_component::<Wallet>(buyer).unwrap().gold, 30);
+        assert_eq!(world.get_component::<Wallet>(seller).unwrap().gold, 20);
+        assert_eq!(resources.get::<ShopDay>().unwrap().ledger.sales, 1);
+    }
+
+    #[test]
+    fn test_failed_sale_leaves_everything_in_place() {
+        let factory = EntityFactory::parse(PRODUCE).unwrap();
+        let mut world = Universe::new().create_world();
+        let apples = item(&mut world, &factory, "apple", 5);
+        let pear = item(&mut world, &factory, "pear", 1);
+        let seller = trader(&mut world, vec![apples], 0);
+        let buyer = trader(&mut world, vec![pear], 50);
+        let resources = settle(&mut world, &factory, Action::Transaction { source: seller, target: buyer, object: apples, count: 2, value: 20 });
+        assert_eq!(world.get_component::<Inventory>(seller).unwrap().contents, vec![apples]);
+        assert_eq!(stacks::quantity(&world, apples), 5);
+        assert_eq!(world.get_component::<Wallet>(buyer).unwrap().gold, 50);
+        let shop_day = resources.get::<ShopDay>().unwrap();
+        assert_eq!((shop_day.ledger.sales, shop_day.ledger.failed_sales), (0, 1));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::component::{ActiveTurn, Parked, Priority, TurnState};
+use legion::prelude::*;
+
+pub struct PendingMoves {
+    list: Vec<Entity>,
+    round: u64,
+}
+
+impl PendingMoves {
+    pub fn new() -> Self {
+        Self {
+            list: vec![],
+            round: 0,
+        }
+    }
+
+    pub fn resume(round: u64) -> Self {
+        Self {
+            list: vec![],
+            round,
+        }
+    }
+
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+}
+
+pub fn turn_system() -> Box<dyn Schedulable> {
+    SystemBuilder::new("turn_system")
+        .write_resource::<PendingMoves>()
+        .read_component::<Priority>()
+        .read_component::<Parked>()
+        .with_query(<Read<ActiveTurn>>::query())
+        .with_query(<Read<Priority>>::query().filter(!component::<Parked>()))
+        .build(
+            move |command_buffer, world, pending_moves, (turn_query, priority_query)| {
+                let mut itr = turn_query.iter_entities(world);
+                let active_entity = itr.next().clone();
+                std::mem::drop(itr);
+                let still_active = match &active_entity {
+                    Some((entity, active_turn)) => {
+                        // A parked entity can't act, so its turn ends when it leaves the level.
+                        if active_turn.state == TurnState::DONE
+                            || world.get_component::<Parked>(*entity).is_some()
+                        {
+                            command_buffer.remove_component::<ActiveTurn>(*entity);
+                            false
+                        } else {
+                            true
+                        }
+                    }
+                    None => false,
+                };
+                std::mem::drop(active_entity);
+                if !still_active {
+                    let mut next_turn = None;
+                    while next_turn.is_none() {
+                        if pending_moves.list.is_empty() {
+                            let mut priority_tuple = vec![];
+
+                            for (entity, priority) in priority_query.iter_entities_mut(world) {
+                                priority_tuple.push((priority.value, entity));
+                            }
+                            priority_tuple.sort_by_key(|k| k.0);
+                            pending_moves.list = priority_tuple.iter().map(|v| v.1).collect();
+                            pending_moves.round += 1;
+                        }
+
+                        let candidate = pending_moves
+                            .list
+                            .pop()
+                            .expect("No entites could take a turn");
+                        // Entities can leave the world or the level between rounds, so skip
+                        // anything stale.
+                        if world.get_component::<Priority>(candidate).is_some()
+                            && world.get_component::<Parked>(candidate).is_none()
+                        {
+                            next_turn = Some(candidate);
+                        }
+                    }
+                    command_buffer.add_component(
+                        next_turn.unwrap(),
+                        ActiveTurn {
+                            state: TurnState::PENDING,
+                        },
+                    );
+                }
+            },
+        )
+}
@@ -0,0 +1,42 @@
+use crate::component::{Player, Position, Viewshed};
+use crate::geom::Point;
+use crate::map::fov::field_of_view;
+use crate::map::Map;
+use crate::message::Message;
+use crate::resources::message_queue::MessageQueue;
+use legion::prelude::*;
+
+pub fn visibility_system() -> Box<dyn Schedulable> {
+    SystemBuilder::new("visibility_system")
+        .write_resource::<Map>()
+        .write_resource::<MessageQueue>()
+        .with_query(<(Write<Viewshed>, Read<Position>)>::query())
+        .with_query(<Read<Viewshed>>::query().filter(tag::<Player>()))
+        .build(move |_, mut world, (map, message_queue), (viewshed_query, player_query)| {
+            let map: &mut Map = map;
+            let message_queue: &mut MessageQueue = message_queue;
+            for (mut viewshed, position) in viewshed_query.iter_mut(&mut world) {
+                let origin: Point = (*position).into();
+                if viewshed.origin == Some(origin) {
+                    continue;
+                }
+                viewshed.visible_tiles = field_of_view(map, origin, viewshed.range);
+                viewshed.origin = Some(origin);
+            }
+
+            let mut revealed = false;
+            for viewshed in player_query.iter(&mut world) {
+                map.clear_visible();
+                for point in viewshed.visible_tiles.iter() {
+                    map.set_visible(*point);
+                    if !map.is_revealed(*point) {
+                        map.set_revealed(*point);
+                        revealed = true;
+                    }
+                }
+            }
+            if revealed {
+                message_queue.push(Message::MapRevealed);
+            }
+        })
+}
@@ -1,71 +1,18 @@
-#![feature(vec_remove_item)]
-
-use crate::glyph::Glyph;
-use crate::map::TileType;
-use crate::server::map_builders::factories::{drunk_builder, random_builder, shop_builder};
-use crate::server::map_builders::{BuiltMap, MapBuilder};
-use crate::server::server::Server;
 use gdnative::*;
-use instant::Instant;
+use god_four_am_server::component;
+use god_four_am_server::color::Color as ServerColor;
+use god_four_am_server::component::{DisplayCabinet, Inventory, Name, NetworkId, Position, Renderable, Wallet};
+use god_four_am_server::map::{Map, TileType};
+use god_four_am_server::message::{Action, Message};
+use god_four_am_server::resources::network_registry::NetworkIdError;
+use god_four_am_server::resources::shop_day::Ledger;
+use god_four_am_server::resources::movement_rules::Topology;
+use god_four_am_server::resources::trade_handler::{Trade, TradeMessage, TradeRequest, TradeState};
+use god_four_am_server::server::Server;
+use god_four_am_server::stacks;
 use legion::prelude::*;
-use legion::query::DefaultFilter;
-use std::collections::{HashMap, HashSet};
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::component::{Name, Inventory, Position, Renderable, DisplayCabinet};
-use crate::client::{Serdent, entity_to_u64};
-use crate::message::{Message, Action};
-use crate::server::resources::trade_handler::{Trade, TradeState, TradeMessage, TradeRequest};
-
-pub mod color;
-pub mod component;
-pub mod geom;
-pub mod glyph;
-pub mod map;
-pub mod message;
-pub mod server;
-
-pub mod client {
-    use legion::entity::Entity;
-    use serde::{Deserialize, Serialize};
-    use std::convert::{From, Into};
-    use gdnative::*;
-    type EType = u64;
-
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
-    pub struct Serdent(pub EType);
-
-    impl From<Entity> for Serdent {
-        fn from(entity: Entity) -> Self {
-            
-            let etype: EType = unsafe { std::mem::transmute(entity) };
-            Serdent(etype)
-        }
-    }
-
-    impl ToVariant for Serdent {
-        fn to_variant(&self) -> Variant {
-            Variant::from_u64(self.0)
-        }
-    }
-
-    impl FromVariant for Serdent {
-        fn from_variant(variant: &Variant) -> Result<Self, FromVariantError> {
-            Ok(Serdent(variant.to_u64()))
-        }
-    }
-
-    impl Into<Entity> for Serdent {
-        fn into(self) -> Entity {
-            unsafe { std::mem::transmute(self.0) }
-        }
-    }
-
-    pub fn entity_to_u64(entity: Entity) -> u64 {
-        let serd: Serdent = entity.into();
-        serd.0
-    }
-}
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(ToVariant, FromVariant)]
 enum TradeStateDTO {
@@ -78,25 +25,48 @@ enum TradeStateDTO {
     Final(u32),
 }
 
+#[derive(ToVariant)]
+struct LedgerDTO {
+    pub day: u32,
+    pub sales: u32,
+    pub revenue: u32,
+    pub failed_sales: u32,
+}
+
+impl From<Ledger> for LedgerDTO {
+    fn from(ledger: Ledger) -> Self {
+        Self {
+            day: ledger.day,
+            sales: ledger.sales,
+            revenue: ledger.revenue,
+            failed_sales: ledger.failed_sales,
+        }
+    }
+}
+
 #[derive(ToVariant)]
 struct TradeDTO {
     pub request: u64,
-    pub target: Serdent,
-    pub buyer: Serdent,
-    pub seller: Serdent,
-    pub last_response: Serdent,
-    pub trade_state: TradeStateDTO
+    pub target: u64,
+    pub count: u32,
+    pub buyer: u64,
+    pub seller: u64,
+    pub last_response: u64,
+    pub trade_state: TradeStateDTO,
+    pub rounds: u32
 }
 
-impl Into<TradeDTO> for Trade {
-    fn into(self) -> TradeDTO {
-        TradeDTO {
-            request: self.request.id,
-            target: self.target.into(),
-            buyer: self.buyer.into(),
-            seller: self.seller.into(),
-            last_response: self.last_response.into(),
-            trade_state: match self.trade_state {
+impl TradeDTO {
+    fn from_trade(trade: Trade, server: &Server) -> Option<TradeDTO> {
+        let id = |entity| server.network_id(entity).map(|id| id.0);
+        Some(TradeDTO {
+            request: trade.request.id,
+            target: id(trade.target)?,
+            count: trade.count,
+            buyer: id(trade.buyer)?,
+            seller: id(trade.seller)?,
+            last_response: id(trade.last_response)?,
+            trade_state: match trade.trade_state {
                 TradeState::Pending => TradeStateDTO::Pending,
                 TradeState::Start => TradeStateDTO::Start,
                 TradeState::Offer(val) => TradeStateDTO::Offer(val),
@@ -104,22 +74,23 @@ impl Into<TradeDTO> for Trade {
                 TradeState::Rejected => TradeStateDTO::Rejected,
                 TradeState::Accepted => TradeStateDTO::Accepted,
                 TradeState::Final(val) => TradeStateDTO::Final(val),
-            }
-        }
+            },
+            rounds: trade.rounds
+        })
     }
 }
 
 #[derive(FromVariant)]
 struct TradeMessageDTO {
-    pub origin: Serdent,
+    pub origin: u64,
     pub request: u64,
     pub state_change: TradeStateDTO
 }
 
-impl Into<TradeMessage> for TradeMessageDTO {
-    fn into(self) -> TradeMessage {
-        TradeMessage {
-            origin: self.origin.into(),
+impl TradeMessageDTO {
+    fn into_message(self, server: &Server) -> Result<TradeMessage, NetworkIdError> {
+        Ok(TradeMessage {
+            origin: server.resolve(NetworkId(self.origin))?,
             request: TradeRequest {
                 id: self.request
             },
@@ -132,7 +103,7 @@ impl Into<TradeMessage> for TradeMessageDTO {
                 TradeStateDTO::Accepted => TradeState::Accepted,
                 TradeStateDTO::Final(val) => TradeState::Final(val),
             }
-        }
+        })
     }
 }
 
@@ -147,12 +118,9 @@ struct EntityTracker {
 
 impl EntityTracker {
     pub fn track(&mut self, world: &legion::prelude::World) -> TrackerResult {
-        let query = <(Read<component::Position>)>::query();
-        let current: HashSet<u64> = query.iter_entities(world).map(
-            |(entity, (_))| {
-                let ser: Serdent = entity.into();
-                ser.0
-            }
+        let query = <(Read<Position>, Read<NetworkId>)>::query();
+        let current: HashSet<u64> = query.iter(world).map(
+            |(_, id)| id.0
         ).collect();
         let deleted: Vec<u64> = self.prev_entities.difference(&current).cloned().collect();
         let created: Vec<u64> = current.difference(&self.prev_entities).cloned().collect();
@@ -170,20 +138,28 @@ impl EntityTracker {
 #[register_with(Self::register_signals)]
 pub struct LogicController {
     server: Server,
-    tracker: EntityTracker
+    tracker: EntityTracker,
+    watch_definitions: bool,
+    since_definitions_check: f64
 }
 
+const DEFINITIONS_CHECK_SECONDS: f64 = 1.0;
+
 #[methods]
 impl LogicController {
     fn _init(mut _owner: Node) -> Self {
-        let server = Server::new();
-        let query = <(Read<component::Position>)>::query();
-        godot_print!("Connected to server.");
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+        let server = Server::new(seed);
+        godot_print!("Connected to server with seed {}.", seed);
         LogicController {
             server,
             tracker: EntityTracker {
                 prev_entities: HashSet::new()
-            }
+            },
+            watch_definitions: false,
+            since_definitions_check: 0.0
         }
     }
 
@@ -227,6 +203,17 @@ impl LogicController {
                 },
             ],
         });
+        builder.add_signal(init::Signal {
+            name: "changed_entities",
+            args: &[
+                init::SignalArgument {
+                    name: "entities",
+                    default: Variant::default(),
+                    export_info: init::ExportInfo::new(VariantType::VariantArray),
+                    usage: init::PropertyUsage::DEFAULT,
+                },
+            ],
+        });
         builder.add_signal(init::Signal {
             name: "trade_event",
             args: &[
@@ -238,6 +225,45 @@ impl LogicController {
                 },
             ],
         });
+        builder.add_signal(init::Signal {
+            name: "action_rejected",
+            args: &[
+                init::SignalArgument {
+                    name: "reason",
+                    default: Variant::default(),
+                    export_info: init::ExportInfo::new(VariantType::GodotString),
+                    usage: init::PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+        builder.add_signal(init::Signal {
+            name: "phase_changed",
+            args: &[
+                init::SignalArgument {
+                    name: "day",
+                    default: Variant::default(),
+                    export_info: init::ExportInfo::new(VariantType::I64),
+                    usage: init::PropertyUsage::DEFAULT,
+                },
+                init::SignalArgument {
+                    name: "phase",
+                    default: Variant::default(),
+                    export_info: init::ExportInfo::new(VariantType::GodotString),
+                    usage: init::PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+        builder.add_signal(init::Signal {
+            name: "day_summary",
+            args: &[
+                init::SignalArgument {
+                    name: "ledger",
+                    default: Variant::default(),
+                    export_info: init::ExportInfo::new(VariantType::Dictionary),
+                    usage: init::PropertyUsage::DEFAULT,
+                },
+            ],
+        });
         builder.add_signal(init::Signal {
             name: "trade_request",
             args: &[
@@ -247,12 +273,16 @@ impl LogicController {
     }
 
     unsafe fn emit_map(&self, mut _owner: Node) {
-        let map = self.server.resources.get::<crate::map::Map>().unwrap();
-        let variant: Vec<String> = map.tiles.iter().map(
-            |tile| match tile {
-                TileType::Wall => "#".to_string(),
-                TileType::Floor => ".".to_string(),
-                TileType::Digging => ">".to_string(),
+        let map = self.server.resources.get::<Map>().unwrap();
+        let variant: Vec<String> = map.tiles.iter().zip(map.revealed_tiles.iter()).map(
+            |(tile, revealed)| match (tile, revealed) {
+                (_, false) => " ".to_string(),
+                (TileType::Wall, _) => "#".to_string(),
+                (TileType::Floor, _) => ".".to_string(),
+                (TileType::Digging, _) => "*".to_string(),
+                (TileType::Door, _) => "+".to_string(),
+                (TileType::DownStairs, _) => ">".to_string(),
+                (TileType::UpStairs, _) => "<".to_string(),
             }
         ).collect();
         godot_print!("Emitting map");
@@ -283,15 +313,48 @@ impl LogicController {
             match message {
                 Message::TradeEvent(trade) => {
                     godot_print!("Processing trade event");
-                    let trade_dto: TradeDTO = trade.into();
-                    _owner.emit_signal(
-                        GodotString::from_str("trade_event"),
-                        &[trade_dto.to_variant()]
-                    );
-                    godot_print!("Processed");
+                    match TradeDTO::from_trade(trade, &self.server) {
+                        Some(trade_dto) => {
+                            _owner.emit_signal(
+                                GodotString::from_str("trade_event"),
+                                &[trade_dto.to_variant()]
+                            );
+                            godot_print!("Processed");
+                        },
+                        None => godot_print!("Dropped trade event for an unregistered entity")
+                    }
                 },
                 Message::LogEvent(event) => {
                     godot_print!("{}", event)
+                },
+                Message::ActionRejected(reason) => {
+                    _owner.emit_signal(
+                        GodotString::from_str("action_rejected"),
+                        &[Variant::from_str(&reason)]
+                    );
+                },
+                Message::PhaseChanged { day, phase } => {
+                    _owner.emit_signal(
+                        GodotString::from_str("phase_changed"),
+                        &[Variant::from_u64(day as u64), Variant::from_str(&format!("{:?}", phase))]
+                    );
+                },
+                Message::DaySummary(ledger) => {
+                    _owner.emit_signal(
+                        GodotString::from_str("day_summary"),
+                        &[LedgerDTO::from(ledger).to_variant()]
+                    );
+                },
+                Message::MapRevealed | Message::MapUpdated => {
+                    self.emit_map(_owner);
+                }
+                Message::EntitiesChanged(entities) => {
+                    let ids: Vec<u64> = entities
+                        .into_iter()
+                        .filter_map(|entity| self.server.network_id(entity))
+                        .map(|id| id.0)
+                        .collect();
+                    self.emit_entities(_owner, "changed_entities", ids);
                 }
             }
         }
@@ -305,6 +368,17 @@ impl LogicController {
 
     #[export]
     fn _process(&mut self, mut _owner: Node, delta: f64) {
+        if self.watch_definitions {
+            self.since_definitions_check += delta;
+            if self.since_definitions_check >= DEFINITIONS_CHECK_SECONDS {
+                self.since_definitions_check = 0.0;
+                match self.server.poll_definitions(true) {
+                    Some(Ok(changed)) => godot_print!("Reloaded entity definitions, {} entities changed.", changed),
+                    Some(Err(error)) => godot_print!("Couldn't reload entity definitions: {}", error),
+                    None => {}
+                }
+            }
+        }
         unsafe {
             let result = self.tracker.track(&self.server.world);
             if !result.created.is_empty() {
@@ -316,11 +390,14 @@ impl LogicController {
         }
     }
 
-    fn get_entity(variant: Variant) -> Option<Entity> {
+    fn get_entity(&self, variant: Variant) -> Option<Entity> {
         match variant.get_type() {
-            VariantType::I64 => {
-                let entity: Entity = Serdent(variant.to_u64()).into();
-                Some(entity)
+            VariantType::I64 => match self.server.resolve(NetworkId(variant.to_u64())) {
+                Ok(entity) => Some(entity),
+                Err(error) => {
+                    godot_print!("Could not resolve entity: {:?}", error);
+                    None
+                }
             },
             _ => None,
         }
@@ -329,7 +406,7 @@ impl LogicController {
     #[export]
     unsafe fn get_name(&self, _owner:Node, entity: Variant) -> GodotString {
         let world = &self.server.world;
-        match Self::get_entity(entity) {
+        match self.get_entity(entity) {
             Some(entity) => world.get_component::<Name>(entity).map_or(GodotString::from_str(""),|name| {
                 GodotString::from_str(&name.name)
             }),
@@ -341,20 +418,21 @@ impl LogicController {
     unsafe fn get_inventory(&self, _owner: Node, variant: Variant) -> VariantArray {
         let mut res: VariantArray = VariantArray::new();
         let world = &self.server.world;
-        match Self::get_entity(variant) {
+        match self.get_entity(variant) {
             Some(entity) => {
                 world.get_component::<Inventory>(entity).map(|inventory| {
                     inventory.contents.iter().for_each(|item| {
                         let name = world.get_component::<Name>(*item);
-                        let ser: Serdent = (*item).into();
-                        match name {
-                            Some(name) => {
+                        let id = self.server.network_id(*item);
+                        match (name, id) {
+                            (Some(name), Some(id)) => {
                                 let mut dictionary: Dictionary = Dictionary::new();
                                 dictionary.set(&Variant::from_str("name"), &Variant::from_str(&name.name));
-                                dictionary.set(&Variant::from_str("entity"), &Variant::from_u64(ser.0));
+                                dictionary.set(&Variant::from_str("entity"), &Variant::from_u64(id.0));
+                                dictionary.set(&Variant::from_str("count"), &Variant::from_u64(stacks::quantity(world, *item) as u64));
                                 res.push(&dictionary.to_variant());
                             },
-                            None => ()
+                            _ => ()
                         };
                     });
                 });
@@ -367,7 +445,7 @@ impl LogicController {
     #[export]
     unsafe fn get_position(&self, _owner: Node, variant: Variant) -> Variant {
         let world = &self.server.world;
-        let res: Vector2 = match Self::get_entity(variant) {
+        let res: Vector2 = match self.get_entity(variant) {
             Some(entity) => {
                 world.get_component::<Position>(entity).map_or((-1.0 as f32, -1.0 as f32).into(),
                   |position| {
@@ -383,7 +461,7 @@ impl LogicController {
     #[export]
     unsafe fn get_renderable(&self, _owner: Node, variant: Variant) -> GodotString {
         let world = &self.server.world;
-        let res: String = match Self::get_entity(variant) {
+        let res: String = match self.get_entity(variant) {
             Some(entity) => {
                 world.get_component::<Renderable>(entity).map_or("".to_string(),
                                                                |renderable| {
@@ -396,10 +474,45 @@ impl LogicController {
         GodotString::from_str(res)
     }
 
+    fn get_glyph_color(&self, variant: Variant, pick: fn(&Renderable) -> Option<ServerColor>) -> Variant {
+        self.get_entity(variant)
+            .and_then(|entity| self.server.world.get_component::<Renderable>(entity).and_then(|renderable| pick(&renderable)))
+            .map_or(Variant::new(), |color| Color::rgba(color.r, color.g, color.b, color.a).to_variant())
+    }
+
+    #[export]
+    unsafe fn get_foreground(&self, _owner: Node, variant: Variant) -> Variant {
+        self.get_glyph_color(variant, |renderable| renderable.glyph.foreground)
+    }
+
+    #[export]
+    unsafe fn get_background(&self, _owner: Node, variant: Variant) -> Variant {
+        self.get_glyph_color(variant, |renderable| renderable.glyph.background)
+    }
+
+    #[export]
+    unsafe fn get_suggested_price(&self, _owner: Node, variant: Variant) -> Variant {
+        self.get_entity(variant)
+            .and_then(|entity| self.server.suggested_price(entity))
+            .map_or(Variant::new(), |price| (price as i64).to_variant())
+    }
+
+    #[export]
+    unsafe fn get_gold(&self, _owner: Node, variant: Variant) -> Variant {
+        let world = &self.server.world;
+        let res: i64 = match self.get_entity(variant) {
+            Some(entity) => world
+                .get_component::<Wallet>(entity)
+                .map_or(0, |wallet| wallet.gold as i64),
+            None => 0
+        };
+        res.to_variant()
+    }
+
     #[export]
     unsafe fn is_display_case(&self, _owner: Node, variant: Variant) -> Variant {
         let world = &self.server.world;
-        let res: bool = match Self::get_entity(variant) {
+        let res: bool = match self.get_entity(variant) {
             Some(entity) => {
                 world.get_tag::<DisplayCabinet>(entity).map_or(false, |_| { true })
             },
@@ -408,6 +521,16 @@ impl LogicController {
         res.to_variant()
     }
 
+    #[export]
+    unsafe fn is_customer(&self, _owner: Node, variant: Variant) -> Variant {
+        let world = &self.server.world;
+        let res: bool = match self.get_entity(variant) {
+            Some(entity) => world.get_component::<component::Customer>(entity).is_some(),
+            None => false
+        };
+        res.to_variant()
+    }
+
     #[export]
     unsafe fn try_move(&mut self, _owner: Node, variant: Variant) {
         match variant.get_type() {
@@ -420,42 +543,143 @@ impl LogicController {
     }
 
     #[export]
-    unsafe fn try_take(&mut self, _owner: Node, variant: Variant) {
-        match Self::get_entity(variant) {
-            Some(entity) => {
-                self.server.try_player_take(entity);
-            },
-            None => ()
+    unsafe fn set_movement_topology(&mut self, _owner: Node, topology: GodotString) -> bool {
+        let topology = match topology.to_string().as_str() {
+            "four_way" => Topology::FourWay,
+            "eight_way" => Topology::EightWay,
+            "eight_way_no_corner_cutting" => Topology::EightWayNoCornerCutting,
+            other => {
+                godot_print!("Unknown movement topology {}", other);
+                return false;
+            }
         };
+        self.server.set_movement_topology(topology);
+        true
+    }
+
+    #[export]
+    unsafe fn try_move_to(&mut self, _owner: Node, variant: Variant) -> bool {
+        match variant.get_type() {
+            VariantType::Vector2 => {
+                let target = variant.to_vector2();
+                self.server.try_move_to((target.x as i32, target.y as i32).into())
+            }
+            _ => false
+        }
     }
 
     #[export]
-    unsafe fn try_trade(&mut self, _owner: Node) {
-        self.server.try_start_trade();
+    unsafe fn try_take(&mut self, _owner: Node, target: Variant, item: Variant, count: u32) {
+        if let Some(target) = self.get_entity(target) {
+            if let Some(item) = self.get_entity(item) {
+                self.server.try_player_take(target, item, count);
+            }
+        }
+    }
+
+    #[export]
+    unsafe fn try_start_trade(&mut self, _owner: Node, buyer: Variant, item: Variant, count: u32) -> bool {
+        match (self.get_entity(buyer), self.get_entity(item)) {
+            (Some(buyer), Some(item)) => self.server.try_start_trade(buyer, item, count),
+            _ => false
+        }
     }
 
     #[export]
-    unsafe fn try_put(&mut self, _owner: Node, target: Variant, item: Variant) {
-        if let Some(target) = Self::get_entity(target) {
-            if let Some(item) = Self::get_entity(item) {
-                self.server.try_player_put(target, item);
+    unsafe fn try_put(&mut self, _owner: Node, target: Variant, item: Variant, count: u32) {
+        if let Some(target) = self.get_entity(target) {
+            if let Some(item) = self.get_entity(item) {
+                self.server.try_player_put(target, item, count);
             }
         }
     }
 
     #[export]
     unsafe fn try_trade_handle(&mut self, _owner: Node, request: Variant) {
-        let dto: TradeMessageDTO = TradeMessageDTO::from_variant(&request).unwrap();
-        let message: TradeMessage = dto.into();
-        godot_print!("Handling trade {:?}", message);
-        self.server.add_action(Action::TradeUpdate(message));
-        godot_print!("Trade pushed to queue");
+        let dto = match TradeMessageDTO::from_variant(&request) {
+            Ok(dto) => dto,
+            Err(error) => {
+                godot_print!("Ignoring malformed trade update: {:?}", error);
+                return;
+            }
+        };
+        match dto.into_message(&self.server) {
+            Ok(message) => {
+                godot_print!("Handling trade {:?}", message);
+                self.server.add_action(Action::TradeUpdate(message));
+                godot_print!("Trade pushed to queue");
+            },
+            Err(error) => godot_print!("Ignoring trade update: {:?}", error)
+        }
+    }
+
+    #[export]
+    unsafe fn preview_generator(&mut self, _owner: Node, name: GodotString) -> bool {
+        self.server.preview_generator(&name.to_string())
+    }
+
+    #[export]
+    unsafe fn reload_definitions(&mut self, _owner: Node, reapply: bool) -> bool {
+        match self.server.reload_definitions(reapply) {
+            Ok(_) => true,
+            Err(error) => {
+                godot_print!("Couldn't reload entity definitions: {}", error);
+                false
+            }
+        }
+    }
+
+    #[export]
+    unsafe fn set_watch_definitions(&mut self, _owner: Node, watch: bool) {
+        self.watch_definitions = watch;
+        self.since_definitions_check = 0.0;
+    }
+
+    #[export]
+    unsafe fn open_shop(&mut self, _owner: Node) -> bool {
+        self.server.open_shop()
+    }
+
+    #[export]
+    unsafe fn pause(&mut self, _owner: Node) {
+        self.server.pause();
+    }
+
+    #[export]
+    unsafe fn resume(&mut self, _owner: Node) {
+        self.server.resume();
+    }
+
+    #[export]
+    unsafe fn save_game(&mut self, _owner: Node, path: GodotString) -> bool {
+        match self.server.save(path.to_string()) {
+            Ok(()) => true,
+            Err(error) => {
+                godot_print!("Failed to save game: {:?}", error);
+                false
+            }
+        }
+    }
+
+    #[export]
+    unsafe fn load_game(&mut self, _owner: Node, path: GodotString) -> bool {
+        match self.server.load(path.to_string()) {
+            Ok(()) => {
+                self.emit_map(_owner);
+                true
+            },
+            Err(error) => {
+                godot_print!("Failed to load game: {:?}", error);
+                false
+            }
+        }
     }
 
     #[export]
     unsafe fn get_player(&mut self, _owner: Node) -> Variant {
-        let ser: Serdent = self.server.get_player().into();
-        ser.0.to_variant()
+        self.server
+            .network_id(self.server.get_player())
+            .map_or(Variant::new(), |id| id.0.to_variant())
     }
 }
 
@@ -1,7 +0,0 @@
-#[derive(PartialEq, Copy, Clone)]
-pub enum RunState {
-    Paused,
-    Initializing,
-    MapGeneration,
-    Running,
-}
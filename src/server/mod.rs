@@ -1,6 +0,0 @@
-pub mod gamestate;
-pub mod map_builders;
-pub mod serializers;
-pub mod server;
-pub mod systems;
-pub mod resources;
\ No newline at end of file
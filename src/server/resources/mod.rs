@@ -1,3 +0,0 @@
-pub mod trade_handler;
-pub mod action_queue;
-pub mod message_queue;
\ No newline at end of file
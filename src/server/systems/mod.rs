@@ -1,4 +0,0 @@
-pub mod index_system;
-pub mod turn_system;
-pub mod trade_system;
-pub mod transaction_system;
\ No newline at end of file